@@ -1,12 +1,15 @@
 //! Volume data access - main API for reading/writing VDS volumes
 
-use crate::compression::get_compressor;
+use crate::brick::{BrickHeader, BRICK_HEADER_SIZE};
+use crate::compression::{Compressor, ZSTD_DICTIONARY_PATH};
 use crate::error::{Result, VdsError};
 use crate::io::{create_io_manager, IOManager};
 use crate::layout::VolumeDataLayout;
-use crate::metadata::VdsMetadata;
-use crate::types::DataType;
-use crate::utils::brick_path;
+use crate::metadata::{BrickMetadata, VdsMetadata, VolumeStatistics};
+use crate::types::{DataType, ValueRange};
+use crate::utils::{
+    brick_index_path, brick_path, bytes_to_f32, bytes_to_f64, copy_box, BufferPool, BufferRegion,
+};
 use bytes::Bytes;
 use futures::future::try_join_all;
 use parking_lot::RwLock;
@@ -20,6 +23,15 @@ pub struct VolumeDataAccess {
 
     /// I/O manager for storage operations
     io_manager: Arc<Box<dyn IOManager>>,
+
+    /// Zstd dictionary used for brick compression, if the volume has one
+    zstd_dictionary: Option<Arc<Vec<u8>>>,
+
+    /// Scratch buffers for bricks that cannot be decoded straight into a slice
+    brick_buffers: BufferPool,
+
+    /// Serializes writes of `metadata.json` so the last one written holds the latest state
+    metadata_writes: tokio::sync::Mutex<()>,
 }
 
 impl VolumeDataAccess {
@@ -32,29 +44,100 @@ impl VolumeDataAccess {
         let metadata_bytes = io_manager.read("metadata.json").await?;
         let metadata: VdsMetadata = serde_json::from_slice(&metadata_bytes)
             .map_err(|e| VdsError::Metadata(e.to_string()))?;
+        metadata.validate_compression()?;
+        let zstd_dictionary =
+            Self::load_zstd_dictionary(io_manager.as_ref().as_ref(), &metadata).await?;
 
         Ok(Self {
             metadata: Arc::new(RwLock::new(metadata)),
             io_manager,
+            zstd_dictionary,
+            brick_buffers: BufferPool::default(),
+            metadata_writes: tokio::sync::Mutex::new(()),
         })
     }
 
     /// Create a new VDS volume
-    pub async fn create(url: impl Into<String>, metadata: VdsMetadata) -> Result<Self> {
+    ///
+    /// Fixes the lossy error bound with [`VdsMetadata::fix_lossy_error_bound`], failing for
+    /// lossy volumes without a value range.
+    pub async fn create(url: impl Into<String>, mut metadata: VdsMetadata) -> Result<Self> {
         let url = url.into();
+        metadata.validate_compression()?;
+        metadata.fix_lossy_error_bound()?;
         let io_manager = Arc::new(create_io_manager(&url).await?);
 
         // Write initial metadata
         let metadata_json =
             serde_json::to_vec_pretty(&metadata).map_err(|e| VdsError::Metadata(e.to_string()))?;
         io_manager.write("metadata.json", &metadata_json).await?;
+        let zstd_dictionary =
+            Self::load_zstd_dictionary(io_manager.as_ref().as_ref(), &metadata).await?;
 
         Ok(Self {
             metadata: Arc::new(RwLock::new(metadata)),
             io_manager,
+            zstd_dictionary,
+            brick_buffers: BufferPool::default(),
+            metadata_writes: tokio::sync::Mutex::new(()),
         })
     }
 
+    /// Create a new VDS volume whose Zstd bricks are compressed with `dictionary`
+    ///
+    /// The dictionary is stored with the volume; train one from sample bricks with
+    /// [`train_zstd_dictionary`](crate::compression::train_zstd_dictionary).
+    pub async fn create_with_zstd_dictionary(
+        url: impl Into<String>,
+        mut metadata: VdsMetadata,
+        dictionary: Vec<u8>,
+    ) -> Result<Self> {
+        let url = url.into();
+        create_io_manager(&url)
+            .await?
+            .write(ZSTD_DICTIONARY_PATH, &dictionary)
+            .await?;
+        metadata.zstd_dictionary = true;
+        Self::create(url, metadata).await
+    }
+
+    /// Create a volume derived from this one, sharing its zstd dictionary if it has one
+    pub(crate) async fn create_derived(
+        &self,
+        url: impl Into<String>,
+        metadata: VdsMetadata,
+    ) -> Result<Self> {
+        match &self.zstd_dictionary {
+            Some(dictionary) => {
+                Self::create_with_zstd_dictionary(url, metadata, dictionary.to_vec()).await
+            }
+            None => Self::create(url, metadata).await,
+        }
+    }
+
+    /// Read the zstd dictionary named by the metadata
+    async fn load_zstd_dictionary(
+        io_manager: &dyn IOManager,
+        metadata: &VdsMetadata,
+    ) -> Result<Option<Arc<Vec<u8>>>> {
+        if !metadata.zstd_dictionary {
+            return Ok(None);
+        }
+        if !io_manager.exists(ZSTD_DICTIONARY_PATH).await? {
+            return Err(VdsError::MissingField(format!(
+                "Metadata requires a zstd dictionary but {} is missing",
+                ZSTD_DICTIONARY_PATH
+            )));
+        }
+        let dictionary = io_manager.read(ZSTD_DICTIONARY_PATH).await?;
+        Ok(Some(Arc::new(dictionary.to_vec())))
+    }
+
+    /// The volume's zstd dictionary, if it has one
+    pub fn zstd_dictionary(&self) -> Option<Arc<Vec<u8>>> {
+        self.zstd_dictionary.clone()
+    }
+
     /// Get the volume metadata
     pub fn metadata(&self) -> VdsMetadata {
         self.metadata.read().clone()
@@ -72,29 +155,10 @@ impl VolumeDataAccess {
     /// * `max_coords` - Maximum coordinates (exclusive)
     ///
     /// # Returns
-    /// Raw bytes containing the data in the slice
+    /// Raw bytes containing the data in the slice, row-major with the last dimension fastest
     pub async fn read_slice(&self, min_coords: &[usize], max_coords: &[usize]) -> Result<Bytes> {
         let layout = self.layout();
-
-        // Validate coordinates
-        if min_coords.len() != layout.dimensionality || max_coords.len() != layout.dimensionality {
-            return Err(VdsError::InvalidDimensions(
-                "Coordinate dimensions don't match volume dimensionality".to_string(),
-            ));
-        }
-
-        for i in 0..layout.dimensionality {
-            if min_coords[i] >= max_coords[i] {
-                return Err(VdsError::InvalidDimensions(
-                    "Min coordinates must be less than max coordinates".to_string(),
-                ));
-            }
-            if !layout.is_in_bounds(min_coords) || !layout.is_in_bounds(max_coords) {
-                return Err(VdsError::OutOfBounds(
-                    "Coordinates out of volume bounds".to_string(),
-                ));
-            }
-        }
+        Self::validate_box(&layout, min_coords, max_coords)?;
 
         // Determine which bricks overlap with the requested slice
         let brick_indices = self.get_overlapping_bricks(min_coords, max_coords);
@@ -106,7 +170,52 @@ impl VolumeDataAccess {
         self.assemble_slice(min_coords, max_coords, &brick_indices, &bricks)
     }
 
+    /// Read a slice in which bricks that were never written read as zeros
+    ///
+    /// Returns `None` if none of the overlapping bricks have been written.
+    pub(crate) async fn read_stored_slice(
+        &self,
+        min_coords: &[usize],
+        max_coords: &[usize],
+    ) -> Result<Option<Bytes>> {
+        let layout = self.layout();
+        Self::validate_box(&layout, min_coords, max_coords)?;
+
+        let stored = try_join_all(
+            self.get_overlapping_bricks(min_coords, max_coords)
+                .into_iter()
+                .map(|index| async move {
+                    let exists = self.io_manager.exists(&brick_path(index, 0)).await?;
+                    Ok::<_, VdsError>(exists.then_some(index))
+                }),
+        )
+        .await?;
+        let stored: Vec<usize> = stored.into_iter().flatten().collect();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let bricks = self.read_bricks(&stored).await?;
+        self.assemble_slice(min_coords, max_coords, &stored, &bricks)
+            .map(Some)
+    }
+
+    /// Read a slice of data decoded to `f32` samples
+    ///
+    /// Any stored data type is converted, including IBM floats (see [`crate::ibm`]).
+    pub async fn read_slice_f32(
+        &self,
+        min_coords: &[usize],
+        max_coords: &[usize],
+    ) -> Result<Vec<f32>> {
+        let data = self.read_slice(min_coords, max_coords).await?;
+        bytes_to_f32(&data, self.layout().data_type)
+    }
+
     /// Write a slice of data
+    ///
+    /// Bricks fully covered by the slice are overwritten; partially covered bricks are read,
+    /// patched and written back. Bricks that don't exist yet start out zero-filled.
     pub async fn write_slice(
         &self,
         min_coords: &[usize],
@@ -114,20 +223,15 @@ impl VolumeDataAccess {
         data: &[u8],
     ) -> Result<()> {
         let layout = self.layout();
+        Self::validate_box(&layout, min_coords, max_coords)?;
 
-        // Validate coordinates and data size
-        if min_coords.len() != layout.dimensionality || max_coords.len() != layout.dimensionality {
-            return Err(VdsError::InvalidDimensions(
-                "Coordinate dimensions don't match volume dimensionality".to_string(),
-            ));
-        }
-
-        let expected_voxels: usize = min_coords
+        let slice_dims: Vec<usize> = min_coords
             .iter()
             .zip(max_coords.iter())
             .map(|(min, max)| max - min)
-            .product();
-        let expected_bytes = expected_voxels * layout.data_type.size_in_bytes();
+            .collect();
+        let element_size = layout.data_type.size_in_bytes();
+        let expected_bytes = slice_dims.iter().product::<usize>() * element_size;
 
         if data.len() != expected_bytes {
             return Err(VdsError::InvalidDimensions(format!(
@@ -137,36 +241,299 @@ impl VolumeDataAccess {
             )));
         }
 
-        // This is a simplified implementation - in practice you'd need to:
-        // 1. Read overlapping bricks
-        // 2. Modify them with new data
-        // 3. Write them back
-        // For now, just return unimplemented
-        Err(VdsError::Configuration(
-            "Write operations not yet fully implemented".to_string(),
-        ))
+        let futures: Vec<_> = self
+            .get_overlapping_bricks(min_coords, max_coords)
+            .into_iter()
+            .map(|index| {
+                let layout = &layout;
+                let slice_dims = &slice_dims;
+                async move {
+                    let range = layout.brick_data_range(&layout.brick_index_to_coords(index));
+                    let brick_min: Vec<usize> = range.iter().map(|r| r.0).collect();
+                    let brick_dims: Vec<usize> = range.iter().map(|r| r.1 - r.0).collect();
+                    let box_min: Vec<usize> = brick_min
+                        .iter()
+                        .zip(min_coords)
+                        .map(|(a, b)| *a.max(b))
+                        .collect();
+                    let box_max: Vec<usize> = range
+                        .iter()
+                        .zip(max_coords)
+                        .map(|(r, &max)| r.1.min(max))
+                        .collect();
+
+                    let fully_covered = box_min == brick_min
+                        && box_max.iter().zip(range.iter()).all(|(a, r)| *a == r.1);
+                    let mut brick =
+                        if !fully_covered && self.io_manager.exists(&brick_path(index, 0)).await? {
+                            self.read_brick(index).await?
+                        } else {
+                            vec![0u8; brick_dims.iter().product::<usize>() * element_size]
+                        };
+
+                    copy_box(
+                        data,
+                        BufferRegion {
+                            origin: min_coords,
+                            dims: slice_dims,
+                        },
+                        &mut brick,
+                        BufferRegion {
+                            origin: &brick_min,
+                            dims: &brick_dims,
+                        },
+                        &box_min,
+                        &box_max,
+                        element_size,
+                    );
+
+                    self.write_brick(index, &brick).await
+                }
+            })
+            .collect();
+
+        try_join_all(futures).await?;
+        Ok(())
+    }
+
+    /// Read and decompress a single brick
+    ///
+    /// Bricks are stored row-major (last dimension fastest) and cover the voxel range given by
+    /// [`VolumeDataLayout::brick_data_range`], so edge bricks are smaller than the brick size.
+    pub async fn read_brick(&self, index: usize) -> Result<Vec<u8>> {
+        self.read_lod_brick(0, index).await
+    }
+
+    /// Read and decompress a single brick of a level of detail
+    ///
+    /// Brick indices refer to the brick grid of [`VolumeDataLayout::lod_layout`].
+    pub async fn read_lod_brick(&self, lod: usize, index: usize) -> Result<Vec<u8>> {
+        let layout = self.layout().lod_layout(lod)?;
+        let brick = self.io_manager.read(&brick_path(index, lod)).await?;
+        self.decode_brick(&brick, layout.brick_data_bytes(index))
+    }
+
+    /// Validate a stored brick's header and decompress it to exactly `expected_size` bytes
+    ///
+    /// The payload is decoded with the codec named in the header. Bricks without a header are
+    /// accepted only from volumes written before
+    /// [`VdsVersion::BRICK_HEADERS`](crate::metadata::VdsVersion::BRICK_HEADERS) and are decoded
+    /// with the volume's compression method.
+    pub(crate) fn decode_brick(&self, brick: &[u8], expected_size: usize) -> Result<Vec<u8>> {
+        let (compressor, offset) = self.brick_decoder(brick, expected_size)?;
+        let data = compressor.decompress(&brick[offset..], Some(expected_size))?;
+        Self::check_brick_size(data.len(), expected_size)?;
+        Ok(data)
+    }
+
+    /// Decode a stored brick into `output`, which must be exactly the brick's size
+    fn decode_brick_into(&self, brick: &[u8], output: &mut [u8]) -> Result<()> {
+        let (compressor, offset) = self.brick_decoder(brick, output.len())?;
+        compressor.decompress_into(&brick[offset..], output)
+    }
+
+    /// Decode a stored brick, returning uncompressed payloads without copying
+    fn decode_brick_bytes(&self, brick: Bytes, expected_size: usize) -> Result<Bytes> {
+        let (compressor, offset) = self.brick_decoder(&brick, expected_size)?;
+        let data = compressor.decompress_bytes(brick.slice(offset..), Some(expected_size))?;
+        Self::check_brick_size(data.len(), expected_size)?;
+        Ok(data)
+    }
+
+    /// Check a stored brick's header and pick the compressor and payload offset for it
+    fn brick_decoder(
+        &self,
+        brick: &[u8],
+        expected_size: usize,
+    ) -> Result<(Box<dyn Compressor>, usize)> {
+        let metadata = self.metadata.read();
+        match BrickHeader::parse(brick)? {
+            Some((header, _)) => {
+                if header.data_type != metadata.layout.data_type {
+                    return Err(VdsError::InvalidFormat(format!(
+                        "Brick holds {} samples, volume is {}",
+                        header.data_type, metadata.layout.data_type
+                    )));
+                }
+                if header.uncompressed_size != expected_size as u64 {
+                    return Err(VdsError::InvalidFormat(format!(
+                        "Brick header records {} bytes, expected {}",
+                        header.uncompressed_size, expected_size
+                    )));
+                }
+                let compressor =
+                    metadata.compressor_for(header.method, self.zstd_dictionary.clone())?;
+                Ok((compressor, BRICK_HEADER_SIZE))
+            }
+            None if !metadata.version.has_brick_headers() => {
+                Ok((metadata.brick_compressor(self.zstd_dictionary.clone())?, 0))
+            }
+            None => Err(VdsError::InvalidFormat(
+                "Brick is missing its header".to_string(),
+            )),
+        }
+    }
+
+    fn check_brick_size(size: usize, expected_size: usize) -> Result<()> {
+        if size != expected_size {
+            return Err(VdsError::Decompression(format!(
+                "Brick decompressed to {} bytes, expected {}",
+                size, expected_size
+            )));
+        }
+        Ok(())
     }
 
-    /// Read specific bricks by their indices
-    async fn read_bricks(&self, indices: &[usize]) -> Result<HashMap<usize, Vec<u8>>> {
-        {
+    /// Compress and write a single brick
+    ///
+    /// Uses [`VdsMetadata::compress_brick`] with the volume's zstd dictionary and prefixes the
+    /// payload with a [`BrickHeader`] naming the codec used. Invalidates the brick index and
+    /// statistics written by [`compute_statistics`](Self::compute_statistics) and cached
+    /// histograms, and widens [`VdsMetadata::value_range`] to cover the brick's finite samples,
+    /// or sets it from them while it is unset.
+    pub async fn write_brick(&self, index: usize, data: &[u8]) -> Result<()> {
+        let layout = self.layout();
+        if index >= layout.total_bricks() {
+            return Err(VdsError::OutOfBounds(format!(
+                "Brick index {} out of range ({} bricks)",
+                index,
+                layout.total_bricks()
+            )));
+        }
+
+        let expected_bytes = layout.brick_data_bytes(index);
+        if data.len() != expected_bytes {
+            return Err(VdsError::InvalidDimensions(format!(
+                "Brick size mismatch: expected {} bytes, got {}",
+                expected_bytes,
+                data.len()
+            )));
+        }
+
+        let (method, compressed) = self
+            .metadata
+            .read()
+            .compress_brick(data, self.zstd_dictionary.clone())?;
+        let header = BrickHeader::new(method, layout.data_type, data.len(), &compressed);
+        self.io_manager
+            .write(&brick_path(index, 0), &header.frame(&compressed))
+            .await?;
+
+        // Statistics and histograms no longer describe the samples, and the value range must
+        // still cover them
+        let written = ValueRange::of_finite(&bytes_to_f64(data, layout.data_type)?);
+        let stale = {
             let metadata = self.metadata.read();
-            let _compressor = get_compressor(metadata.compression);
+            metadata.statistics.is_some()
+                || !metadata.histograms.is_empty()
+                || written.is_some_and(|range| {
+                    !metadata.has_value_range() || !metadata.value_range.covers(&range)
+                })
+        };
+        if stale {
+            self.update_metadata(|metadata| {
+                metadata.statistics = None;
+                metadata.histograms.clear();
+                if let Some(range) = written {
+                    let current = metadata.value_range;
+                    metadata.value_range = if metadata.has_value_range() {
+                        ValueRange::new(current.min.min(range.min), current.max.max(range.max))
+                    } else {
+                        range
+                    };
+                }
+            })
+            .await?;
+        }
+
+        // Value ranges in the brick index no longer hold once a brick changes. Another writer
+        // or statistics pass may have written it since, so it is removed on every write.
+        self.delete_if_exists(&brick_index_path(0)).await
+    }
+
+    /// Delete `path`, succeeding if it is already gone
+    async fn delete_if_exists(&self, path: &str) -> Result<()> {
+        match self.io_manager.delete(path).await {
+            Err(VdsError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(VdsError::NotFound(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Read the brick index written by the last statistics pass, if any
+    pub async fn read_brick_index(&self) -> Result<Option<Vec<BrickMetadata>>> {
+        let path = brick_index_path(0);
+        if !self.io_manager.exists(&path).await? {
+            return Ok(None);
+        }
+        let bytes = self.io_manager.read(&path).await?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| VdsError::Metadata(e.to_string()))
+    }
+
+    /// Write the brick index
+    pub async fn write_brick_index(&self, bricks: &[BrickMetadata]) -> Result<()> {
+        let json =
+            serde_json::to_vec(bricks).map_err(|e| VdsError::Serialization(e.to_string()))?;
+        self.io_manager.write(&brick_index_path(0), &json).await
+    }
+
+    /// Apply `update` to the metadata, touch it and persist it to `metadata.json`
+    pub(crate) async fn update_metadata(
+        &self,
+        update: impl FnOnce(&mut VdsMetadata),
+    ) -> Result<()> {
+        let _guard = self.metadata_writes.lock().await;
+        let json = {
+            let mut metadata = self.metadata.write();
+            update(&mut metadata);
+            metadata.touch();
+            serde_json::to_vec_pretty(&*metadata).map_err(|e| VdsError::Metadata(e.to_string()))?
+        };
+        self.io_manager.write("metadata.json", &json).await
+    }
+
+    /// Get the I/O manager backing this volume
+    pub(crate) fn io_manager(&self) -> &dyn IOManager {
+        self.io_manager.as_ref().as_ref()
+    }
+
+    /// Validate a `[min, max)` voxel box against the layout
+    pub(crate) fn validate_box(
+        layout: &VolumeDataLayout,
+        min_coords: &[usize],
+        max_coords: &[usize],
+    ) -> Result<()> {
+        if min_coords.len() != layout.dimensionality || max_coords.len() != layout.dimensionality {
+            return Err(VdsError::InvalidDimensions(
+                "Coordinate dimensions don't match volume dimensionality".to_string(),
+            ));
         }
 
+        for i in 0..layout.dimensionality {
+            if min_coords[i] >= max_coords[i] {
+                return Err(VdsError::InvalidDimensions(
+                    "Min coordinates must be less than max coordinates".to_string(),
+                ));
+            }
+            if max_coords[i] > layout.axes[i].num_samples {
+                return Err(VdsError::OutOfBounds(
+                    "Coordinates out of volume bounds".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read specific stored bricks by their indices, without decoding them
+    async fn read_bricks(&self, indices: &[usize]) -> Result<HashMap<usize, Bytes>> {
         // Read all bricks concurrently
         let futures: Vec<_> = indices
             .iter()
-            .map(|&index| {
-                let io_manager = Arc::clone(&self.io_manager);
-                let compressor = get_compressor(self.metadata.read().compression);
-
-                async move {
-                    let path = brick_path(index, 0);
-                    let compressed = io_manager.read(&path).await?;
-                    let decompressed = compressor.decompress(&compressed, None)?;
-                    Ok::<_, VdsError>((index, decompressed))
-                }
+            .map(|&index| async move {
+                Ok::<_, VdsError>((index, self.io_manager.read(&brick_path(index, 0)).await?))
             })
             .collect();
 
@@ -177,7 +544,6 @@ impl VolumeDataAccess {
     /// Get brick indices that overlap with a slice
     fn get_overlapping_bricks(&self, min_coords: &[usize], max_coords: &[usize]) -> Vec<usize> {
         let layout = self.layout();
-        let _brick_count = layout.brick_count();
         let mut brick_indices = Vec::new();
 
         // Calculate min/max brick coordinates
@@ -228,15 +594,20 @@ impl VolumeDataAccess {
         }
     }
 
-    /// Assemble a slice from brick data
+    /// Assemble a slice from stored bricks
+    ///
+    /// Bricks that form a contiguous run of the slice are decoded straight into it, and a slice
+    /// covering exactly one uncompressed brick shares the stored bytes. Other bricks are decoded
+    /// into pooled scratch buffers and copied.
     fn assemble_slice(
         &self,
         min_coords: &[usize],
         max_coords: &[usize],
         brick_indices: &[usize],
-        bricks: &HashMap<usize, Vec<u8>>,
+        bricks: &HashMap<usize, Bytes>,
     ) -> Result<Bytes> {
         let layout = self.layout();
+        let element_size = layout.data_type.size_in_bytes();
 
         // Calculate slice dimensions
         let slice_dims: Vec<usize> = min_coords
@@ -244,21 +615,68 @@ impl VolumeDataAccess {
             .zip(max_coords.iter())
             .map(|(min, max)| max - min)
             .collect();
+        let slice_region = BufferRegion {
+            origin: min_coords,
+            dims: &slice_dims,
+        };
 
         let slice_voxels: usize = slice_dims.iter().product();
-        let slice_bytes = slice_voxels * layout.data_type.size_in_bytes();
-        let mut slice_data = vec![0u8; slice_bytes];
-
-        // This is a simplified implementation
-        // In practice, you'd need to properly copy voxels from bricks to the slice
-        // accounting for brick boundaries, overlap, etc.
-
-        // For now, just return the first brick's data or empty
-        if let Some(&first_index) = brick_indices.first() {
-            if let Some(brick_data) = bricks.get(&first_index) {
-                let copy_len = slice_data.len().min(brick_data.len());
-                slice_data[..copy_len].copy_from_slice(&brick_data[..copy_len]);
+        let mut slice_data = vec![0u8; slice_voxels * element_size];
+
+        for index in brick_indices {
+            let brick = bricks
+                .get(index)
+                .ok_or_else(|| VdsError::NotFound(format!("Brick {}", index)))?;
+
+            let range = layout.brick_data_range(&layout.brick_index_to_coords(*index));
+            let brick_min: Vec<usize> = range.iter().map(|r| r.0).collect();
+            let brick_dims: Vec<usize> = range.iter().map(|r| r.1 - r.0).collect();
+            let brick_region = BufferRegion {
+                origin: &brick_min,
+                dims: &brick_dims,
+            };
+            let brick_bytes = brick_dims.iter().product::<usize>() * element_size;
+
+            // Intersection of the brick and the requested slice
+            let box_min: Vec<usize> = brick_min
+                .iter()
+                .zip(min_coords)
+                .map(|(a, b)| *a.max(b))
+                .collect();
+            let box_max: Vec<usize> = range
+                .iter()
+                .zip(max_coords)
+                .map(|(r, &max)| r.1.min(max))
+                .collect();
+
+            let inside = box_min == brick_min && box_max.iter().zip(&range).all(|(a, r)| *a == r.1);
+            if inside && brick_indices.len() == 1 && brick_dims == slice_dims {
+                return self.decode_brick_bytes(brick.clone(), brick_bytes);
+            }
+            if let Some(offset) = inside
+                .then(|| slice_region.contiguous_offset(&brick_region))
+                .flatten()
+            {
+                let start = offset * element_size;
+                self.decode_brick_into(brick, &mut slice_data[start..start + brick_bytes])?;
+                continue;
+            }
+
+            let mut scratch = self.brick_buffers.take(brick_bytes);
+            let decoded = self.decode_brick_into(brick, &mut scratch);
+            if decoded.is_ok() {
+                copy_box(
+                    &scratch,
+                    brick_region,
+                    &mut slice_data,
+                    slice_region,
+                    &box_min,
+                    &box_max,
+                    element_size,
+                );
             }
+            self.brick_buffers.give(scratch);
+            decoded?;
         }
 
         Ok(Bytes::from(slice_data))
@@ -276,6 +694,8 @@ impl VolumeDataAccess {
             uncompressed_size: layout.total_size_bytes(),
             data_type: layout.data_type,
             compression_method: metadata.compression,
+            value_range: metadata.value_range,
+            statistics: metadata.statistics,
         }
     }
 }
@@ -289,11 +709,14 @@ pub struct VolumeStats {
     pub uncompressed_size: usize,
     pub data_type: DataType,
     pub compression_method: crate::compression::CompressionMethod,
+    pub value_range: ValueRange,
+    /// Sample statistics, if a statistics pass has been run
+    pub statistics: Option<VolumeStatistics>,
 }
 
 impl VolumeStats {
     pub fn summary(&self) -> String {
-        format!(
+        let summary = format!(
             "{}D Volume: {} voxels, {} bricks, {} uncompressed ({:?}, {:?})",
             self.dimensionality,
             self.total_voxels,
@@ -301,13 +724,26 @@ impl VolumeStats {
             crate::utils::format_bytes(self.uncompressed_size),
             self.data_type,
             self.compression_method,
-        )
+        );
+        match &self.statistics {
+            Some(stats) => format!(
+                "{}, range {} to {}, mean {}, std {}, rms {}",
+                summary, stats.min, stats.max, stats.mean, stats.std_dev, stats.rms
+            ),
+            None => summary,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::compression::{
+        AdaptiveCompression, CompressionMethod, CompressionParams, ZstdParams,
+    };
+    use crate::filter::Filter;
+    use crate::layout::BrickSize;
+    use crate::metadata::VdsVersion;
     use crate::types::AxisDescriptor;
     use tempfile::TempDir;
 
@@ -333,4 +769,552 @@ mod tests {
         assert_eq!(stats.dimensionality, 3);
         assert_eq!(stats.total_voxels, 100 * 100 * 100);
     }
+
+    #[tokio::test]
+    async fn test_write_and_read_slice_across_bricks() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = temp_dir.path().to_str().unwrap();
+
+        let axes = vec![
+            AxisDescriptor::new(10, "X", "m", 0.0, 9.0),
+            AxisDescriptor::new(7, "Y", "m", 0.0, 6.0),
+            AxisDescriptor::new(5, "Z", "m", 0.0, 4.0),
+        ];
+        let layout = VolumeDataLayout::new(3, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(crate::layout::BrickSize::new([4, 4, 4, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(url, VdsMetadata::new(layout))
+            .await
+            .unwrap();
+
+        let values: Vec<f32> = (0..10 * 7 * 5).map(|v| v as f32).collect();
+        vds.write_slice(
+            &[0, 0, 0],
+            &[10, 7, 5],
+            &crate::utils::typed_data_to_bytes(&values),
+        )
+        .await
+        .unwrap();
+
+        // Full volume round trip
+        let data = vds.read_slice(&[0, 0, 0], &[10, 7, 5]).await.unwrap();
+        let read: Vec<f32> = crate::utils::bytes_to_typed_data(&data).unwrap();
+        assert_eq!(read, values);
+
+        // Sub-box straddling brick boundaries
+        let data = vds.read_slice(&[3, 2, 1], &[6, 5, 4]).await.unwrap();
+        let read: Vec<f32> = crate::utils::bytes_to_typed_data(&data).unwrap();
+        assert_eq!(read.len(), 27);
+        assert_eq!(read[0], (3 * 35 + 2 * 5 + 1) as f32);
+        assert_eq!(read[26], (5 * 35 + 4 * 5 + 3) as f32);
+
+        // Partial overwrite keeps the surrounding voxels
+        vds.write_slice(
+            &[4, 4, 4],
+            &[5, 5, 5],
+            &crate::utils::typed_data_to_bytes(&[-1.0f32]),
+        )
+        .await
+        .unwrap();
+        let data = vds.read_slice(&[4, 4, 3], &[5, 5, 5]).await.unwrap();
+        let read: Vec<f32> = crate::utils::bytes_to_typed_data(&data).unwrap();
+        assert_eq!(read, vec![(4 * 35 + 4 * 5 + 3) as f32, -1.0]);
+    }
+
+    #[tokio::test]
+    async fn test_read_slice_decode_paths() {
+        for method in [CompressionMethod::None, CompressionMethod::Zstd] {
+            let temp_dir = TempDir::new().unwrap();
+            let url = temp_dir.path().to_str().unwrap();
+            let axes = vec![
+                AxisDescriptor::new(10, "X", "m", 0.0, 9.0),
+                AxisDescriptor::new(4, "Y", "m", 0.0, 3.0),
+            ];
+            let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+                .unwrap()
+                .with_brick_size(BrickSize::new([4, 4, 1, 1, 1, 1]));
+            let vds =
+                VolumeDataAccess::create(url, VdsMetadata::new(layout).with_compression(method))
+                    .await
+                    .unwrap();
+            let values: Vec<f32> = (0..40).map(|v| v as f32).collect();
+            vds.write_slice(
+                &[0, 0],
+                &[10, 4],
+                &crate::utils::typed_data_to_bytes(&values),
+            )
+            .await
+            .unwrap();
+
+            // Whole bricks are contiguous runs of the slice
+            assert_eq!(vds.read_slice_f32(&[0, 0], &[10, 4]).await.unwrap(), values);
+            // Exactly one brick, including the smaller edge brick
+            assert_eq!(
+                vds.read_slice_f32(&[8, 0], &[10, 4]).await.unwrap(),
+                values[32..]
+            );
+            // Partial bricks go through scratch buffers
+            assert_eq!(
+                vds.read_slice_f32(&[3, 1], &[6, 3]).await.unwrap(),
+                vec![13.0, 14.0, 17.0, 18.0, 21.0, 22.0]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_ibm_slice_as_f32() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = temp_dir.path().to_str().unwrap();
+
+        let axes = vec![
+            AxisDescriptor::new(2, "X", "m", 0.0, 1.0),
+            AxisDescriptor::new(3, "Y", "m", 0.0, 2.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::IbmF32, axes).unwrap();
+        let vds = VolumeDataAccess::create(url, VdsMetadata::new(layout))
+            .await
+            .unwrap();
+
+        let values = [1.0f32, -118.625, 0.15625, 0.0, 2.5, -0.25];
+        vds.write_slice(&[0, 0], &[2, 3], &crate::ibm::encode_be(&values))
+            .await
+            .unwrap();
+        assert_eq!(vds.read_slice_f32(&[0, 0], &[2, 3]).await.unwrap(), values);
+    }
+
+    #[tokio::test]
+    async fn test_brick_headers() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = temp_dir.path().to_str().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(8, "X", "m", 0.0, 7.0),
+            AxisDescriptor::new(6, "Y", "m", 0.0, 5.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::U16, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 4, 1, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(
+            url,
+            VdsMetadata::new(layout).with_compression(CompressionMethod::LZ4),
+        )
+        .await
+        .unwrap();
+        let data: Vec<u8> = (0..16u16).flat_map(|v| v.to_ne_bytes()).collect();
+        vds.write_brick(0, &data).await.unwrap();
+
+        let brick = vds.io_manager().read(&brick_path(0, 0)).await.unwrap();
+        let (header, _) = BrickHeader::parse(&brick).unwrap().unwrap();
+        assert_eq!(header.method, CompressionMethod::LZ4);
+        assert_eq!(header.data_type, DataType::U16);
+        assert_eq!(header.uncompressed_size, 32);
+
+        // The header's codec is used even if the volume's method changes
+        vds.update_metadata(|m| m.compression = CompressionMethod::Deflate)
+            .await
+            .unwrap();
+        assert_eq!(vds.read_brick(0).await.unwrap(), data);
+
+        // Truncated bricks are rejected instead of decoding to short data
+        vds.io_manager()
+            .write(&brick_path(0, 0), &brick[..brick.len() - 1])
+            .await
+            .unwrap();
+        assert!(vds.read_brick(0).await.is_err());
+
+        // Bare payloads are only accepted from volumes written before brick headers
+        let payload = crate::compression::get_compressor(CompressionMethod::Deflate)
+            .unwrap()
+            .compress(&data)
+            .unwrap();
+        vds.io_manager()
+            .write(&brick_path(0, 0), &payload)
+            .await
+            .unwrap();
+        assert!(vds.read_brick(0).await.is_err());
+        vds.update_metadata(|m| m.version = VdsVersion::new(3, 0))
+            .await
+            .unwrap();
+        assert_eq!(vds.read_brick(0).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_and_constant_bricks() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = temp_dir.path().to_str().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(64, "X", "m", 0.0, 63.0),
+            AxisDescriptor::new(32, "Y", "m", 0.0, 31.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([32, 32, 1, 1, 1, 1]));
+        let metadata = VdsMetadata::new(layout).with_adaptive_compression(
+            AdaptiveCompression::smallest(vec![CompressionMethod::None, CompressionMethod::Zstd]),
+        );
+        let vds = VolumeDataAccess::create(url, metadata).await.unwrap();
+
+        // Brick 0 is constant, brick 1 compresses well
+        let values: Vec<f32> = (0..64 * 32)
+            .map(|i| if i < 32 * 32 { -3.0 } else { (i % 7) as f32 })
+            .collect();
+        vds.write_slice(
+            &[0, 0],
+            &[64, 32],
+            &crate::utils::typed_data_to_bytes(&values),
+        )
+        .await
+        .unwrap();
+
+        let mut methods = Vec::new();
+        for index in 0..2 {
+            let brick = vds.io_manager().read(&brick_path(index, 0)).await.unwrap();
+            let (header, payload) = BrickHeader::parse(&brick).unwrap().unwrap();
+            methods.push((header.method, payload.len()));
+        }
+        assert_eq!(methods[0], (CompressionMethod::Constant, 4));
+        assert_eq!(methods[1].0, CompressionMethod::Zstd);
+
+        let vds = VolumeDataAccess::open(url).await.unwrap();
+        assert_eq!(
+            vds.read_slice_f32(&[0, 0], &[64, 32]).await.unwrap(),
+            values
+        );
+
+        // Without adaptive selection constant bricks are still stored as one value
+        vds.update_metadata(|m| m.adaptive_compression = None)
+            .await
+            .unwrap();
+        vds.write_brick(1, &crate::utils::typed_data_to_bytes(&[7.0f32; 32 * 32]))
+            .await
+            .unwrap();
+        let brick = vds.io_manager().read(&brick_path(1, 0)).await.unwrap();
+        assert_eq!(brick.len(), crate::brick::BRICK_HEADER_SIZE + 4);
+        assert_eq!(
+            vds.read_slice_f32(&[32, 0], &[33, 1]).await.unwrap(),
+            vec![7.0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_brick_removes_brick_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(4, "X", "m", 0.0, 3.0),
+            AxisDescriptor::new(4, "Y", "m", 0.0, 3.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::U8, axes).unwrap();
+        let vds =
+            VolumeDataAccess::create(temp_dir.path().to_str().unwrap(), VdsMetadata::new(layout))
+                .await
+                .unwrap();
+        let index = vec![BrickMetadata::new(0, 16, 16)];
+
+        // Writes succeed whether or not there is an index to remove
+        vds.write_brick_index(&index).await.unwrap();
+        vds.write_brick(0, &[1; 16]).await.unwrap();
+        assert!(vds.read_brick_index().await.unwrap().is_none());
+        vds.write_brick(0, &[2; 16]).await.unwrap();
+
+        // An index written through another handle is removed too
+        let other = VolumeDataAccess::open(temp_dir.path().to_str().unwrap())
+            .await
+            .unwrap();
+        other.write_brick_index(&index).await.unwrap();
+        vds.write_brick(0, &[3; 16]).await.unwrap();
+        assert!(other.read_brick_index().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_zstd_large_window_decodes_into_buffer() {
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(8, "X", "m", 0.0, 7.0),
+            AxisDescriptor::new(16, "Y", "m", 0.0, 15.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::U8, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 16, 1, 1, 1, 1]));
+        let params = CompressionParams {
+            zstd: ZstdParams::new(3).with_window_log(30),
+            ..CompressionParams::default()
+        };
+        let metadata = VdsMetadata::new(layout).with_compression_params(params);
+        let vds = VolumeDataAccess::create(temp_dir.path().to_str().unwrap(), metadata)
+            .await
+            .unwrap();
+        let data: Vec<u8> = (0..128).map(|i| (i % 7) as u8).collect();
+        vds.write_slice(&[0, 0], &[8, 16], &data).await.unwrap();
+        assert_eq!(vds.read_slice(&[0, 0], &[8, 16]).await.unwrap(), data);
+
+        // Streamed frames declare the whole window rather than their content size
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 3).unwrap();
+        encoder.window_log(30).unwrap();
+        encoder.write_all(&data[..32]).unwrap();
+        encoder.flush().unwrap();
+        encoder.write_all(&data[32..64]).unwrap();
+        let payload = encoder.finish().unwrap();
+        let brick =
+            BrickHeader::new(CompressionMethod::Zstd, DataType::U8, 64, &payload).frame(&payload);
+        let mut output = vec![0u8; 64];
+        vds.decode_brick_into(&brick, &mut output).unwrap();
+        assert_eq!(output, &data[..64]);
+    }
+
+    /// XORs every byte, standing in for an in-house codec
+    struct XorCompressor;
+
+    impl Compressor for XorCompressor {
+        fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data.iter().map(|b| b ^ 0x5a).collect())
+        }
+
+        fn decompress(&self, data: &[u8], _expected_size: Option<usize>) -> Result<Vec<u8>> {
+            self.compress(data)
+        }
+
+        fn method(&self) -> CompressionMethod {
+            CompressionMethod::Custom(240)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_codec_volume() {
+        let temp_dir = TempDir::new().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(6, "X", "m", 0.0, 5.0),
+            AxisDescriptor::new(5, "Y", "m", 0.0, 4.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::U8, axes).unwrap();
+
+        // Codec 241 is never registered
+        let url = temp_dir.path().join("unregistered");
+        let metadata =
+            VdsMetadata::new(layout.clone()).with_compression(CompressionMethod::Custom(241));
+        assert!(matches!(
+            VolumeDataAccess::create(url.to_str().unwrap(), metadata).await,
+            Err(VdsError::UnregisteredCodec(241))
+        ));
+
+        crate::compression::register_compressor(240, || Box::new(XorCompressor)).unwrap();
+        let url = temp_dir.path().join("custom");
+        let url = url.to_str().unwrap();
+        let metadata = VdsMetadata::new(layout).with_compression(CompressionMethod::Custom(240));
+        let vds = VolumeDataAccess::create(url, metadata).await.unwrap();
+        let data: Vec<u8> = (0..30).collect();
+        vds.write_slice(&[0, 0], &[6, 5], &data).await.unwrap();
+
+        let brick = vds.io_manager().read(&brick_path(0, 0)).await.unwrap();
+        let (header, payload) = BrickHeader::parse(&brick).unwrap().unwrap();
+        assert_eq!(header.method, CompressionMethod::Custom(240));
+        assert_eq!(payload[1], 1 ^ 0x5a);
+        let vds = VolumeDataAccess::open(url).await.unwrap();
+        assert_eq!(vds.read_slice(&[0, 0], &[6, 5]).await.unwrap(), data);
+
+        // A brick naming an unregistered codec fails with its id
+        let payload = [0u8; 30];
+        let header = BrickHeader::new(CompressionMethod::Custom(241), DataType::U8, 30, &payload);
+        vds.io_manager()
+            .write(&brick_path(0, 0), &header.frame(&payload))
+            .await
+            .unwrap();
+        assert!(matches!(
+            vds.read_brick(0).await,
+            Err(VdsError::UnregisteredCodec(241))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quantized_volume_error_bound() {
+        let temp_dir = TempDir::new().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(16, "X", "m", 0.0, 15.0),
+            AxisDescriptor::new(64, "Y", "m", 0.0, 63.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes).unwrap();
+        let values: Vec<f32> = (0..16 * 64)
+            .map(|i| 100.0 * (i as f32 * 0.05).sin())
+            .collect();
+        let bytes = crate::utils::typed_data_to_bytes(&values);
+
+        for allow_lossy in [true, false] {
+            let url = temp_dir.path().join(allow_lossy.to_string());
+            let metadata = VdsMetadata::new(layout.clone())
+                .with_compression(CompressionMethod::Quantized)
+                .with_compression_tolerance(1.0)
+                .with_value_range(ValueRange::new(-100.0, 100.0))
+                .with_allow_lossy_compression(allow_lossy);
+            let vds = VolumeDataAccess::create(url.to_str().unwrap(), metadata)
+                .await
+                .unwrap();
+            vds.write_slice(&[0, 0], &[16, 64], &bytes).await.unwrap();
+
+            let read = vds.read_slice_f32(&[0, 0], &[16, 64]).await.unwrap();
+            let max_error = values
+                .iter()
+                .zip(&read)
+                .map(|(a, b)| (a - b).abs() as f64)
+                .fold(0.0, f64::max);
+            if allow_lossy {
+                assert!(max_error > 0.0 && max_error <= 200.0 / 255.0);
+            } else {
+                assert_eq!(read, values);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quantized_error_bound_fixed_at_create() {
+        let temp_dir = TempDir::new().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(16, "X", "m", 0.0, 15.0),
+            AxisDescriptor::new(64, "Y", "m", 0.0, 63.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 64, 1, 1, 1, 1]));
+        let values: Vec<f32> = (0..16 * 64)
+            .map(|i| (i / 64) as f32 * 10.0 + (i as f32 * 0.05).sin())
+            .collect();
+        let metadata = VdsMetadata::new(layout)
+            .with_compression(CompressionMethod::Quantized)
+            .with_compression_tolerance(1.0);
+
+        // The default range gives no bound to derive
+        let url = temp_dir.path().join("unset");
+        assert!(matches!(
+            VolumeDataAccess::create(url.to_str().unwrap(), metadata.clone()).await,
+            Err(VdsError::Configuration(_))
+        ));
+
+        // Writes widen the value range but not the bound, whatever their order
+        let metadata = metadata.with_value_range(ValueRange::new(0.0, 25.5));
+        let mut reads = Vec::new();
+        for (name, rows) in [("forward", [0, 4, 8, 12]), ("reverse", [12, 8, 4, 0])] {
+            let url = temp_dir.path().join(name);
+            let vds = VolumeDataAccess::create(url.to_str().unwrap(), metadata.clone())
+                .await
+                .unwrap();
+            for row in rows {
+                let rows = &values[row * 64..(row + 4) * 64];
+                vds.write_slice(
+                    &[row, 0],
+                    &[row + 4, 64],
+                    &crate::utils::typed_data_to_bytes(rows),
+                )
+                .await
+                .unwrap();
+            }
+            let vds = VolumeDataAccess::open(url.to_str().unwrap()).await.unwrap();
+            assert!(vds.metadata().value_range.max > 150.0);
+            assert_eq!(vds.metadata().lossy_max_error(), Some(0.1));
+            reads.push(vds.read_slice_f32(&[0, 0], &[16, 64]).await.unwrap());
+        }
+        assert_eq!(reads[0], reads[1]);
+        assert!(values
+            .iter()
+            .zip(&reads[0])
+            .all(|(a, b)| ((a - b).abs() as f64) <= 0.1));
+    }
+
+    #[tokio::test]
+    async fn test_filtered_volume_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = temp_dir.path().to_str().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(8, "X", "m", 0.0, 7.0),
+            AxisDescriptor::new(50, "Y", "m", 0.0, 49.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes).unwrap();
+        let metadata =
+            VdsMetadata::new(layout).with_filters(vec![Filter::ByteShuffle(4), Filter::Delta(1)]);
+        let vds = VolumeDataAccess::create(url, metadata).await.unwrap();
+
+        let values: Vec<f32> = (0..400).map(|i| (i as f32 * 0.1).cos()).collect();
+        vds.write_slice(
+            &[0, 0],
+            &[8, 50],
+            &crate::utils::typed_data_to_bytes(&values),
+        )
+        .await
+        .unwrap();
+
+        // The filter chain is persisted and reversed on read
+        let vds = VolumeDataAccess::open(url).await.unwrap();
+        assert_eq!(vds.metadata().filters.len(), 2);
+        assert_eq!(vds.read_slice_f32(&[0, 0], &[8, 50]).await.unwrap(), values);
+    }
+
+    #[tokio::test]
+    async fn test_zstd_dictionary_volume() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = temp_dir.path().join("source");
+        let url = url.to_str().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(256, "X", "m", 0.0, 255.0),
+            AxisDescriptor::new(256, "Y", "m", 0.0, 255.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([16, 16, 1, 1, 1, 1]));
+        let values: Vec<f32> = (0..256 * 256)
+            .map(|i| ((i % 256) as f32 * 0.3).sin().round() + (i / 4096) as f32)
+            .collect();
+
+        let samples: Vec<Vec<u8>> = (0..64)
+            .map(|b| crate::utils::typed_data_to_bytes(&values[b * 256..(b + 1) * 256]))
+            .collect();
+        let dictionary = crate::compression::train_zstd_dictionary(&samples, 4096).unwrap();
+        let vds = VolumeDataAccess::create_with_zstd_dictionary(
+            url,
+            VdsMetadata::new(layout.clone()),
+            dictionary.clone(),
+        )
+        .await
+        .unwrap();
+        vds.write_slice(
+            &[0, 0],
+            &[256, 256],
+            &crate::utils::typed_data_to_bytes(&values),
+        )
+        .await
+        .unwrap();
+
+        // Bricks need the stored dictionary to decode
+        let vds = VolumeDataAccess::open(url).await.unwrap();
+        assert_eq!(vds.zstd_dictionary().unwrap().as_slice(), dictionary);
+        assert_eq!(
+            vds.read_slice_f32(&[0, 0], &[256, 256]).await.unwrap(),
+            values
+        );
+        let brick = vds.io_manager().read(&brick_path(0, 0)).await.unwrap();
+        let (_, payload) = BrickHeader::parse(&brick).unwrap().unwrap();
+        assert!(
+            crate::compression::get_compressor(crate::compression::CompressionMethod::Zstd)
+                .unwrap()
+                .decompress(payload, None)
+                .is_err()
+        );
+
+        // Derived volumes carry the dictionary along
+        let crop = temp_dir.path().join("crop");
+        let crop = vds
+            .extract_subvolume(&[0, 0], &[32, 32], crop.to_str().unwrap())
+            .await
+            .unwrap();
+        assert!(crop.metadata().zstd_dictionary);
+        assert_eq!(
+            crop.read_slice_f32(&[0, 0], &[1, 32]).await.unwrap(),
+            values[..32]
+        );
+
+        // Metadata naming a dictionary that is not stored cannot be opened
+        let url = temp_dir.path().join("missing");
+        let mut metadata = VdsMetadata::new(layout);
+        metadata.zstd_dictionary = true;
+        assert!(VolumeDataAccess::create(url.to_str().unwrap(), metadata)
+            .await
+            .is_err());
+    }
 }
@@ -0,0 +1,188 @@
+//! Brick file framing
+//!
+//! Every brick file starts with a fixed-size header recording how its payload was compressed,
+//! the element type and uncompressed size it decodes to, and a CRC32 of the payload, so a
+//! reader does not have to trust volume-wide metadata and truncated or corrupted bricks are
+//! rejected instead of decoding to short data.
+//!
+//! Header layout (little-endian, [`BRICK_HEADER_SIZE`] bytes):
+//!
+//! | Offset | Size | Field |
+//! |--------|------|-------|
+//! | 0 | 4 | Magic, [`BRICK_MAGIC`] |
+//! | 4 | 1 | Header format version, [`BRICK_FORMAT_VERSION`] |
+//! | 5 | 1 | [`CompressionMethod`] id |
+//! | 6 | 1 | [`DataType`] id |
+//! | 7 | 1 | Reserved, zero |
+//! | 8 | 8 | Uncompressed size in bytes |
+//! | 16 | 8 | Payload size in bytes |
+//! | 24 | 4 | CRC32 of the payload |
+
+use crate::compression::CompressionMethod;
+use crate::error::{Result, VdsError};
+use crate::types::DataType;
+use crate::utils::calculate_checksum;
+
+/// Magic bytes at the start of every brick file
+pub const BRICK_MAGIC: &[u8; 4] = b"VDSB";
+
+/// Version of the brick header format written by this library
+pub const BRICK_FORMAT_VERSION: u8 = 1;
+
+/// Size in bytes of the brick header
+pub const BRICK_HEADER_SIZE: usize = 28;
+
+/// Header at the start of a brick file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrickHeader {
+    /// Header format version
+    pub version: u8,
+
+    /// Codec the payload was compressed with
+    pub method: CompressionMethod,
+
+    /// Element type of the uncompressed data
+    pub data_type: DataType,
+
+    /// Size of the uncompressed data in bytes
+    pub uncompressed_size: u64,
+
+    /// Size of the compressed payload in bytes
+    pub payload_size: u64,
+
+    /// CRC32 of the compressed payload
+    pub checksum: u32,
+}
+
+impl BrickHeader {
+    /// Header for `payload`, compressed with `method` from `uncompressed_size` bytes
+    pub fn new(
+        method: CompressionMethod,
+        data_type: DataType,
+        uncompressed_size: usize,
+        payload: &[u8],
+    ) -> Self {
+        Self {
+            version: BRICK_FORMAT_VERSION,
+            method,
+            data_type,
+            uncompressed_size: uncompressed_size as u64,
+            payload_size: payload.len() as u64,
+            checksum: calculate_checksum(payload),
+        }
+    }
+
+    /// Serialize the header
+    pub fn to_bytes(&self) -> [u8; BRICK_HEADER_SIZE] {
+        let mut bytes = [0u8; BRICK_HEADER_SIZE];
+        bytes[..4].copy_from_slice(BRICK_MAGIC);
+        bytes[4] = self.version;
+        bytes[5] = self.method.id();
+        bytes[6] = self.data_type as u8;
+        bytes[8..16].copy_from_slice(&self.uncompressed_size.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.payload_size.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    /// Split a brick file into its header and payload
+    ///
+    /// Returns `None` for files that do not start with [`BRICK_MAGIC`], such as bricks written
+    /// before headers were introduced. Rejects unknown versions or ids, truncated or oversized
+    /// payloads and checksum mismatches.
+    pub fn parse(brick: &[u8]) -> Result<Option<(Self, &[u8])>> {
+        if !brick.starts_with(BRICK_MAGIC) {
+            return Ok(None);
+        }
+        let header = brick.get(..BRICK_HEADER_SIZE).ok_or_else(|| {
+            VdsError::InvalidFormat(format!("Brick header truncated to {} bytes", brick.len()))
+        })?;
+
+        let version = header[4];
+        if version != BRICK_FORMAT_VERSION {
+            return Err(VdsError::InvalidFormat(format!(
+                "Unsupported brick header version {}",
+                version
+            )));
+        }
+        let method = CompressionMethod::from_u8(header[5]).ok_or_else(|| {
+            VdsError::InvalidFormat(format!("Unknown brick codec id {}", header[5]))
+        })?;
+        let data_type = DataType::from_u8(header[6]).ok_or_else(|| {
+            VdsError::InvalidFormat(format!("Unknown brick data type id {}", header[6]))
+        })?;
+        let header = Self {
+            version,
+            method,
+            data_type,
+            uncompressed_size: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+            payload_size: u64::from_le_bytes(header[16..24].try_into().unwrap()),
+            checksum: u32::from_le_bytes(header[24..28].try_into().unwrap()),
+        };
+
+        let payload = &brick[BRICK_HEADER_SIZE..];
+        if payload.len() as u64 != header.payload_size {
+            return Err(VdsError::InvalidFormat(format!(
+                "Brick payload is {} bytes, header records {}",
+                payload.len(),
+                header.payload_size
+            )));
+        }
+        if calculate_checksum(payload) != header.checksum {
+            return Err(VdsError::InvalidFormat(
+                "Brick payload checksum mismatch".to_string(),
+            ));
+        }
+        Ok(Some((header, payload)))
+    }
+
+    /// Prepend the header to `payload`
+    pub fn frame(&self, payload: &[u8]) -> Vec<u8> {
+        let mut brick = Vec::with_capacity(BRICK_HEADER_SIZE + payload.len());
+        brick.extend_from_slice(&self.to_bytes());
+        brick.extend_from_slice(payload);
+        brick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brick_header_round_trip() {
+        let payload = b"compressed payload".to_vec();
+        let header = BrickHeader::new(CompressionMethod::LZ4, DataType::F32, 4096, &payload);
+        let brick = header.frame(&payload);
+        assert_eq!(brick.len(), BRICK_HEADER_SIZE + payload.len());
+
+        let (parsed, parsed_payload) = BrickHeader::parse(&brick).unwrap().unwrap();
+        assert_eq!(parsed, header);
+        assert_eq!(parsed_payload, payload);
+
+        // Bare payloads have no header
+        assert!(BrickHeader::parse(&payload).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_brick_header_rejects_damage() {
+        let payload = vec![7u8; 100];
+        let brick =
+            BrickHeader::new(CompressionMethod::Zstd, DataType::U8, 100, &payload).frame(&payload);
+
+        assert!(BrickHeader::parse(&brick[..brick.len() - 1]).is_err());
+        assert!(BrickHeader::parse(&brick[..10]).is_err());
+
+        let mut corrupted = brick.clone();
+        corrupted[BRICK_HEADER_SIZE + 5] ^= 1;
+        assert!(BrickHeader::parse(&corrupted).is_err());
+
+        let mut unknown = brick.clone();
+        unknown[5] = 100;
+        assert!(BrickHeader::parse(&unknown).is_err());
+
+        let mut future = brick;
+        future[4] = BRICK_FORMAT_VERSION + 1;
+        assert!(BrickHeader::parse(&future).is_err());
+    }
+}
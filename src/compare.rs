@@ -0,0 +1,400 @@
+//! Error metrics between two volumes
+//!
+//! Used to quantify the damage done by lossy codecs and tolerances: the reference volume is read
+//! brick by brick, the matching box of the test volume is read through `read_slice`, so the two
+//! volumes may use different brick sizes, codecs and sample types. Test bricks that were never
+//! written read as zeros, as they do for `write_slice`.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::types::DataType;
+use crate::utils::{brick_path, bytes_to_f64, typed_data_to_bytes};
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+/// Number of bricks compared concurrently
+const MAX_CONCURRENT_BRICKS: usize = 16;
+
+/// How to compare two volumes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompareOptions {
+    /// Absolute error above which a voxel counts as out of tolerance
+    pub tolerance: f64,
+    /// Write `test - reference` as an `F32` volume at this URL
+    pub difference_url: Option<String>,
+}
+
+impl CompareOptions {
+    /// Compare with the given absolute tolerance
+    pub fn new(tolerance: f64) -> Self {
+        Self {
+            tolerance,
+            difference_url: None,
+        }
+    }
+
+    /// Also write the difference volume to `url`
+    pub fn with_difference_volume(mut self, url: impl Into<String>) -> Self {
+        self.difference_url = Some(url.into());
+        self
+    }
+}
+
+/// Error metrics of one reference brick
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrickError {
+    /// Brick index in the reference volume
+    pub index: usize,
+    /// Largest absolute error
+    pub max_abs_error: f64,
+    /// Root mean square error
+    pub rmse: f64,
+    /// Voxels whose absolute error exceeds the tolerance
+    pub above_tolerance: u64,
+}
+
+/// Error metrics of a test volume against a reference volume
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonReport {
+    /// Voxels where both volumes are finite
+    pub voxel_count: u64,
+    /// Largest absolute error
+    pub max_abs_error: f64,
+    /// Voxel of the largest absolute error
+    pub max_error_voxel: Option<Vec<usize>>,
+    /// Root mean square error
+    pub rmse: f64,
+    /// Peak signal-to-noise ratio in dB, with the reference value range as peak; infinite when
+    /// the volumes are identical
+    pub psnr: f64,
+    /// Ratio of reference to error power in dB; infinite when the volumes are identical
+    pub snr: f64,
+    /// Voxels whose absolute error exceeds the tolerance
+    pub above_tolerance: u64,
+    /// Voxels where exactly one volume is finite, or the non-finite values differ
+    pub non_finite_mismatches: u64,
+    /// Reference bricks that are not stored and were skipped
+    pub bricks_missing: usize,
+    /// Reference bricks whose box holds no stored test bricks and was compared against zeros
+    pub test_bricks_missing: usize,
+    /// Per-brick metrics of the compared bricks, in brick order
+    pub bricks: Vec<BrickError>,
+}
+
+impl ComparisonReport {
+    /// The `n` bricks with the largest absolute error, worst first
+    pub fn hotspots(&self, n: usize) -> Vec<&BrickError> {
+        let mut bricks: Vec<&BrickError> = self.bricks.iter().collect();
+        bricks.sort_by(|a, b| b.max_abs_error.total_cmp(&a.max_abs_error));
+        bricks.truncate(n);
+        bricks
+    }
+}
+
+/// Running error sums, mergeable across bricks
+#[derive(Debug, Clone, Default)]
+struct ErrorSum {
+    count: u64,
+    error_power: f64,
+    signal_power: f64,
+    max_abs_error: f64,
+    max_error_voxel: Option<Vec<usize>>,
+    above_tolerance: u64,
+    non_finite_mismatches: u64,
+    reference_min: f64,
+    reference_max: f64,
+}
+
+impl ErrorSum {
+    fn merge(&mut self, other: &ErrorSum) {
+        if other.count > 0 {
+            if self.count == 0 {
+                self.reference_min = other.reference_min;
+                self.reference_max = other.reference_max;
+            } else {
+                self.reference_min = self.reference_min.min(other.reference_min);
+                self.reference_max = self.reference_max.max(other.reference_max);
+            }
+        }
+        if other.max_error_voxel.is_some()
+            && (self.max_error_voxel.is_none() || other.max_abs_error > self.max_abs_error)
+        {
+            self.max_abs_error = other.max_abs_error;
+            self.max_error_voxel = other.max_error_voxel.clone();
+        }
+        self.count += other.count;
+        self.error_power += other.error_power;
+        self.signal_power += other.signal_power;
+        self.above_tolerance += other.above_tolerance;
+        self.non_finite_mismatches += other.non_finite_mismatches;
+    }
+
+    fn rmse(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.error_power / self.count as f64).sqrt()
+        }
+    }
+}
+
+impl VolumeDataAccess {
+    /// Compare `test` against this volume as the reference
+    ///
+    /// Both volumes must have the same number of samples along every axis.
+    pub async fn compare(
+        &self,
+        test: &VolumeDataAccess,
+        options: &CompareOptions,
+    ) -> Result<ComparisonReport> {
+        let reference = self.metadata();
+        let layout = &reference.layout;
+        if layout.size() != test.layout().size() {
+            return Err(VdsError::InvalidDimensions(format!(
+                "Cannot compare volumes of size {:?} and {:?}",
+                layout.size(),
+                test.layout().size()
+            )));
+        }
+
+        let difference = match &options.difference_url {
+            Some(url) => {
+                let mut difference_layout = layout.clone();
+                difference_layout.data_type = DataType::F32;
+                difference_layout.lod_levels = 1;
+                let mut metadata = reference.derived(difference_layout);
+                metadata.add_metadata("attribute", "difference");
+                Some(self.create_derived(url.clone(), metadata).await?)
+            }
+            None => None,
+        };
+
+        let scans = stream::iter(0..layout.total_bricks())
+            .map(|index| {
+                let difference = difference.as_ref();
+                async move {
+                    if !self.io_manager().exists(&brick_path(index, 0)).await? {
+                        return Ok::<_, VdsError>(None);
+                    }
+                    let coords = layout.brick_index_to_coords(index);
+                    let range = layout.brick_data_range(&coords);
+                    let min: Vec<usize> = range.iter().map(|&(start, _)| start).collect();
+                    let max: Vec<usize> = range.iter().map(|&(_, end)| end).collect();
+                    let dims: Vec<usize> = range.iter().map(|(start, end)| end - start).collect();
+
+                    let expected = bytes_to_f64(&self.read_brick(index).await?, layout.data_type)?;
+                    let (actual, test_missing) = match test.read_stored_slice(&min, &max).await? {
+                        Some(data) => (bytes_to_f64(&data, test.layout().data_type)?, false),
+                        None => (vec![0.0; expected.len()], true),
+                    };
+
+                    let mut sum = ErrorSum::default();
+                    let mut max_error_index = None;
+                    let mut diff = Vec::with_capacity(expected.len());
+                    for (i, (&e, &a)) in expected.iter().zip(&actual).enumerate() {
+                        diff.push((a - e) as f32);
+                        if !e.is_finite() || !a.is_finite() {
+                            let same = (e.is_nan() && a.is_nan()) || e == a;
+                            if !same {
+                                sum.non_finite_mismatches += 1;
+                            }
+                            continue;
+                        }
+
+                        let error = a - e;
+                        if sum.count == 0 {
+                            sum.reference_min = e;
+                            sum.reference_max = e;
+                        } else {
+                            sum.reference_min = sum.reference_min.min(e);
+                            sum.reference_max = sum.reference_max.max(e);
+                        }
+                        sum.count += 1;
+                        sum.error_power += error * error;
+                        sum.signal_power += e * e;
+                        if error.abs() > options.tolerance {
+                            sum.above_tolerance += 1;
+                        }
+                        if max_error_index.is_none() || error.abs() > sum.max_abs_error {
+                            sum.max_abs_error = error.abs();
+                            max_error_index = Some(i);
+                        }
+                    }
+
+                    // Unravel the brick-local index of the largest error (last dimension fastest)
+                    sum.max_error_voxel = max_error_index.map(|mut rest| {
+                        let mut voxel = min.clone();
+                        for dim in (0..dims.len()).rev() {
+                            voxel[dim] += rest % dims[dim];
+                            rest /= dims[dim];
+                        }
+                        voxel
+                    });
+
+                    if let Some(difference) = difference {
+                        difference
+                            .write_brick(index, &typed_data_to_bytes(&diff))
+                            .await?;
+                    }
+
+                    let brick = BrickError {
+                        index,
+                        max_abs_error: sum.max_abs_error,
+                        rmse: sum.rmse(),
+                        above_tolerance: sum.above_tolerance,
+                    };
+                    Ok(Some((brick, sum, test_missing)))
+                }
+            })
+            .buffered(MAX_CONCURRENT_BRICKS);
+
+        let (bricks, total, missing, test_missing) = scans
+            .try_fold(
+                (Vec::new(), ErrorSum::default(), 0, 0),
+                |(mut bricks, mut total, mut missing, mut test_missing), scan| {
+                    match scan {
+                        Some((brick, sum, test_brick_missing)) => {
+                            total.merge(&sum);
+                            bricks.push(brick);
+                            test_missing += test_brick_missing as usize;
+                        }
+                        None => missing += 1,
+                    }
+                    futures::future::ready(Ok((bricks, total, missing, test_missing)))
+                },
+            )
+            .await?;
+
+        let rmse = total.rmse();
+        let (psnr, snr) = if total.error_power == 0.0 {
+            (f64::INFINITY, f64::INFINITY)
+        } else {
+            (
+                20.0 * ((total.reference_max - total.reference_min) / rmse).log10(),
+                10.0 * (total.signal_power / total.error_power).log10(),
+            )
+        };
+
+        Ok(ComparisonReport {
+            voxel_count: total.count,
+            max_abs_error: total.max_abs_error,
+            max_error_voxel: total.max_error_voxel,
+            rmse,
+            psnr,
+            snr,
+            above_tolerance: total.above_tolerance,
+            non_finite_mismatches: total.non_finite_mismatches,
+            bricks_missing: missing,
+            test_bricks_missing: test_missing,
+            bricks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::metadata::VdsMetadata;
+    use crate::types::AxisDescriptor;
+    use tempfile::TempDir;
+
+    async fn create_volume(
+        path: &std::path::Path,
+        brick: usize,
+        values: &[f32],
+    ) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(4, "Inline", "unitless", 0.0, 3.0),
+            AxisDescriptor::new(6, "Sample", "ms", 0.0, 20.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([brick, brick, 1, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(path.to_str().unwrap(), VdsMetadata::new(layout))
+            .await
+            .unwrap();
+        vds.write_slice(&[0, 0], &[4, 6], &typed_data_to_bytes(values))
+            .await
+            .unwrap();
+        vds
+    }
+
+    #[tokio::test]
+    async fn test_compare_identical() {
+        let dir = TempDir::new().unwrap();
+        let values: Vec<f32> = (0..24).map(|i| i as f32).collect();
+        let a = create_volume(&dir.path().join("a"), 4, &values).await;
+        let b = create_volume(&dir.path().join("b"), 2, &values).await;
+
+        let report = a.compare(&b, &CompareOptions::new(0.0)).await.unwrap();
+        assert_eq!(report.voxel_count, 24);
+        assert_eq!((report.max_abs_error, report.rmse), (0.0, 0.0));
+        assert_eq!(report.psnr, f64::INFINITY);
+        assert_eq!(report.bricks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_compare_errors() {
+        let dir = TempDir::new().unwrap();
+        let reference: Vec<f32> = (0..24).map(|i| i as f32 - 12.0).collect();
+        let mut test = reference.clone();
+        test[2 * 6 + 5] += 3.0;
+        test[0] -= 1.0;
+        test[1] = f32::NAN;
+        let a = create_volume(&dir.path().join("a"), 4, &reference).await;
+        let b = create_volume(&dir.path().join("b"), 4, &test).await;
+
+        let options = CompareOptions::new(0.5)
+            .with_difference_volume(dir.path().join("diff").to_str().unwrap());
+        let report = a.compare(&b, &options).await.unwrap();
+        assert_eq!(report.voxel_count, 23);
+        assert_eq!(report.non_finite_mismatches, 1);
+        assert_eq!(report.above_tolerance, 2);
+        assert_eq!(report.max_abs_error, 3.0);
+        assert_eq!(report.max_error_voxel, Some(vec![2, 5]));
+        let rmse = (10.0f64 / 23.0).sqrt();
+        assert!((report.rmse - rmse).abs() < 1e-12);
+        assert!((report.psnr - 20.0 * (23.0 / rmse).log10()).abs() < 1e-9);
+        let signal: f64 = reference
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != 1)
+            .map(|(_, &v)| (v as f64).powi(2))
+            .sum();
+        assert!((report.snr - 10.0 * (signal / 10.0).log10()).abs() < 1e-9);
+
+        // Brick (0, 1) holds voxel (2, 5)
+        let worst = report.hotspots(1);
+        assert_eq!(worst[0].index, 1);
+        assert_eq!(worst[0].above_tolerance, 1);
+
+        let diff = VolumeDataAccess::open(dir.path().join("diff").to_str().unwrap())
+            .await
+            .unwrap();
+        let data = diff.read_slice_f32(&[0, 0], &[4, 6]).await.unwrap();
+        assert_eq!((data[0], data[17], data[3]), (-1.0, 3.0, 0.0));
+        assert!(data[1].is_nan());
+    }
+
+    #[tokio::test]
+    async fn test_compare_sparse_test_volume() {
+        let dir = TempDir::new().unwrap();
+        let values: Vec<f32> = (0..24).map(|i| i as f32 + 1.0).collect();
+        let a = create_volume(&dir.path().join("a"), 2, &values).await;
+
+        // Only the first row of test bricks is written
+        let path = dir.path().join("b");
+        let b = VolumeDataAccess::create(path.to_str().unwrap(), a.metadata().derived(a.layout()))
+            .await
+            .unwrap();
+        b.write_slice(&[0, 0], &[2, 6], &typed_data_to_bytes(&values[..12]))
+            .await
+            .unwrap();
+
+        let report = a.compare(&b, &CompareOptions::new(0.0)).await.unwrap();
+        assert_eq!((report.bricks_missing, report.test_bricks_missing), (0, 3));
+        assert_eq!(report.voxel_count, 24);
+        assert_eq!(report.above_tolerance, 12);
+        assert_eq!(report.max_abs_error, 24.0);
+    }
+}
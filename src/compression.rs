@@ -1,29 +1,51 @@
 //! Compression and decompression for VDS data
 
 use crate::error::{Result, VdsError};
+use bytes::Bytes;
 use flate2::read::{ZlibDecoder, ZlibEncoder};
 use flate2::Compression as FlateCompression;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::Read;
+use std::sync::{Arc, OnceLock};
+
+/// First codec id available to codecs registered with [`register_compressor`]; lower ids are
+/// reserved for built-in codecs
+pub const FIRST_CUSTOM_CODEC_ID: u8 = 128;
 
 /// Compression methods supported by VDS
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CompressionMethod {
     /// No compression
-    None = 0,
+    None,
     /// Deflate/ZIP compression
-    Deflate = 1,
+    Deflate,
     /// Run-length encoding
-    RLE = 2,
+    RLE,
     /// Zstandard compression
-    Zstd = 3,
+    Zstd,
     /// Wavelet compression (Bluware proprietary - placeholder)
-    Wavelet = 4,
+    Wavelet,
+    /// Error-bounded quantization of F32 samples followed by Zstandard
+    Quantized,
+    /// Run-length encoding over whole data-type elements with varint run lengths
+    ElementRLE,
+    /// LZ4 block compression, optimised for decode speed
+    LZ4,
+    /// LZ4 high-compression mode; slower to write, decoded like [`CompressionMethod::LZ4`]
+    LZ4HC,
+    /// A brick whose elements are all equal, stored as that single element
+    Constant,
+    /// A codec registered at runtime, with an id of at least [`FIRST_CUSTOM_CODEC_ID`]
+    Custom(u8),
 }
 
 impl CompressionMethod {
     /// Get the method from a byte value
+    ///
+    /// Ids from [`FIRST_CUSTOM_CODEC_ID`] up map to [`CompressionMethod::Custom`], whether or
+    /// not a codec is registered for them.
     pub fn from_u8(value: u8) -> Option<Self> {
         match value {
             0 => Some(CompressionMethod::None),
@@ -31,61 +53,367 @@ impl CompressionMethod {
             2 => Some(CompressionMethod::RLE),
             3 => Some(CompressionMethod::Zstd),
             4 => Some(CompressionMethod::Wavelet),
+            5 => Some(CompressionMethod::Quantized),
+            6 => Some(CompressionMethod::ElementRLE),
+            7 => Some(CompressionMethod::LZ4),
+            8 => Some(CompressionMethod::LZ4HC),
+            9 => Some(CompressionMethod::Constant),
+            id if id >= FIRST_CUSTOM_CODEC_ID => Some(CompressionMethod::Custom(id)),
             _ => None,
         }
     }
+
+    /// Codec id stored in brick headers
+    pub fn id(&self) -> u8 {
+        match self {
+            CompressionMethod::None => 0,
+            CompressionMethod::Deflate => 1,
+            CompressionMethod::RLE => 2,
+            CompressionMethod::Zstd => 3,
+            CompressionMethod::Wavelet => 4,
+            CompressionMethod::Quantized => 5,
+            CompressionMethod::ElementRLE => 6,
+            CompressionMethod::LZ4 => 7,
+            CompressionMethod::LZ4HC => 8,
+            CompressionMethod::Constant => 9,
+            CompressionMethod::Custom(id) => *id,
+        }
+    }
+
+    /// Built-in codecs, all pre-registered
+    pub const BUILT_IN: [CompressionMethod; 10] = [
+        CompressionMethod::None,
+        CompressionMethod::Deflate,
+        CompressionMethod::RLE,
+        CompressionMethod::Zstd,
+        CompressionMethod::Wavelet,
+        CompressionMethod::Quantized,
+        CompressionMethod::ElementRLE,
+        CompressionMethod::LZ4,
+        CompressionMethod::LZ4HC,
+        CompressionMethod::Constant,
+    ];
 }
 
-/// Compression level (0-9, where 0 is no compression and 9 is maximum)
-#[derive(Debug, Clone, Copy)]
-pub struct CompressionLevel(u8);
+/// How [`AdaptiveCompression`] picks a codec for each brick
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CodecSelection {
+    /// Keep the smallest output
+    Smallest,
+    /// Keep the first candidate whose output is at most this many times the size of the
+    /// smallest; list candidates fastest to decode first
+    FastestWithin(f64),
+}
+
+/// Per-brick codec selection: every brick is compressed with each candidate and one is kept
+///
+/// The chosen codec is recorded in each brick's [`BrickHeader`](crate::brick::BrickHeader), so
+/// volumes mixing codecs decode transparently.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveCompression {
+    /// Codecs to try, in order of preference
+    pub candidates: Vec<CompressionMethod>,
+    /// How the codec is picked among the candidates
+    pub selection: CodecSelection,
+}
+
+impl AdaptiveCompression {
+    /// Keep the smallest of `candidates`
+    pub fn smallest(candidates: Vec<CompressionMethod>) -> Self {
+        Self {
+            candidates,
+            selection: CodecSelection::Smallest,
+        }
+    }
+
+    /// Keep the first of `candidates` within `max_size_ratio` of the smallest
+    pub fn fastest_within(candidates: Vec<CompressionMethod>, max_size_ratio: f64) -> Self {
+        Self {
+            candidates,
+            selection: CodecSelection::FastestWithin(max_size_ratio),
+        }
+    }
+
+    /// Check there is a candidate and the size ratio is at least one
+    pub fn validate(&self) -> Result<()> {
+        if self.candidates.is_empty() {
+            return Err(VdsError::Configuration(
+                "Adaptive compression needs at least one candidate codec".to_string(),
+            ));
+        }
+        if let CodecSelection::FastestWithin(ratio) = self.selection {
+            if ratio.is_nan() || ratio < 1.0 {
+                return Err(VdsError::Configuration(format!(
+                    "Adaptive compression size ratio {} must be at least 1",
+                    ratio
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Compress `data` with every candidate, as built by `compressor_for`, and keep one
+    pub fn compress(
+        &self,
+        data: &[u8],
+        compressor_for: impl Fn(CompressionMethod) -> Result<Box<dyn Compressor>>,
+    ) -> Result<(CompressionMethod, Vec<u8>)> {
+        self.validate()?;
+        let outputs = self
+            .candidates
+            .iter()
+            .map(|&method| {
+                let compressor = compressor_for(method)?;
+                Ok((compressor.method(), compressor.compress(data)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let smallest = outputs.iter().map(|(_, c)| c.len()).min().unwrap_or(0);
+        let limit = match self.selection {
+            CodecSelection::Smallest => smallest,
+            CodecSelection::FastestWithin(ratio) => (smallest as f64 * ratio) as usize,
+        };
+        Ok(outputs
+            .into_iter()
+            .find(|(_, compressed)| compressed.len() <= limit)
+            .expect("the smallest output is within the limit"))
+    }
+}
+
+/// Parameters for every codec; the volume's [`CompressionMethod`] selects which ones apply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionParams {
+    /// Parameters for [`CompressionMethod::Deflate`]
+    pub deflate: DeflateParams,
+    /// Parameters for [`CompressionMethod::Zstd`] and the Zstandard stage of
+    /// [`CompressionMethod::Quantized`]
+    pub zstd: ZstdParams,
+    /// Parameters for [`CompressionMethod::LZ4`]
+    pub lz4: LZ4Params,
+    /// Parameters for [`CompressionMethod::LZ4HC`]
+    pub lz4hc: LZ4HCParams,
+}
+
+impl CompressionParams {
+    /// Check every codec's parameters
+    pub fn validate(&self) -> Result<()> {
+        self.deflate.validate()?;
+        self.zstd.validate()?;
+        self.lz4.validate()?;
+        self.lz4hc.validate()
+    }
+}
+
+/// Deflate parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeflateParams {
+    /// Compression level, 0 (store) to 9 (best)
+    pub level: u32,
+}
+
+impl DeflateParams {
+    /// Deflate at `level`
+    pub fn new(level: u32) -> Self {
+        Self { level }
+    }
+
+    /// Check the level is in range
+    pub fn validate(&self) -> Result<()> {
+        if self.level > 9 {
+            return Err(VdsError::Configuration(format!(
+                "Deflate level {} is outside 0-9",
+                self.level
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for DeflateParams {
+    fn default() -> Self {
+        Self { level: 6 }
+    }
+}
+
+const ZSTD_WINDOW_LOG_MIN: u32 = 10;
+const ZSTD_WINDOW_LOG_MAX: u32 = 31;
+
+/// Zstandard parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZstdParams {
+    /// Compression level; negative levels trade ratio for speed, up to 22 for the best ratio
+    pub level: i32,
+    /// Base-2 logarithm of the match window (10-31); `None` lets zstd choose from the level
+    pub window_log: Option<u32>,
+    /// Long-distance matching, which finds repeats far apart in large bricks
+    pub long_distance_matching: bool,
+}
 
-impl CompressionLevel {
-    pub fn new(level: u8) -> Self {
-        Self(level.min(9))
+impl ZstdParams {
+    /// Zstandard at `level`
+    pub fn new(level: i32) -> Self {
+        Self {
+            level,
+            ..Self::default()
+        }
+    }
+
+    /// Set the match window size as a power of two
+    pub fn with_window_log(mut self, window_log: u32) -> Self {
+        self.window_log = Some(window_log);
+        self
+    }
+
+    /// Enable or disable long-distance matching
+    pub fn with_long_distance_matching(mut self, enabled: bool) -> Self {
+        self.long_distance_matching = enabled;
+        self
+    }
+
+    /// Check the level and window size are supported by zstd
+    pub fn validate(&self) -> Result<()> {
+        let levels = zstd::compression_level_range();
+        if !levels.contains(&self.level) {
+            return Err(VdsError::Configuration(format!(
+                "Zstd level {} is outside {} to {}",
+                self.level,
+                levels.start(),
+                levels.end()
+            )));
+        }
+        if let Some(window_log) = self.window_log {
+            if !(ZSTD_WINDOW_LOG_MIN..=ZSTD_WINDOW_LOG_MAX).contains(&window_log) {
+                return Err(VdsError::Configuration(format!(
+                    "Zstd window log {} is outside {}-{}",
+                    window_log, ZSTD_WINDOW_LOG_MIN, ZSTD_WINDOW_LOG_MAX
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ZstdParams {
+    fn default() -> Self {
+        Self {
+            level: 6,
+            window_log: None,
+            long_distance_matching: false,
+        }
+    }
+}
+
+/// LZ4 parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LZ4Params {
+    /// Acceleration factor; 1 is the default speed, larger values compress faster and worse
+    pub acceleration: i32,
+}
+
+impl LZ4Params {
+    /// LZ4 with the given acceleration
+    pub fn new(acceleration: i32) -> Self {
+        Self { acceleration }
     }
 
-    pub fn none() -> Self {
-        Self(0)
+    /// Check the acceleration is positive
+    pub fn validate(&self) -> Result<()> {
+        if self.acceleration < 1 {
+            return Err(VdsError::Configuration(format!(
+                "LZ4 acceleration {} must be at least 1",
+                self.acceleration
+            )));
+        }
+        Ok(())
     }
+}
 
-    pub fn fast() -> Self {
-        Self(1)
+impl Default for LZ4Params {
+    fn default() -> Self {
+        Self { acceleration: 1 }
     }
+}
+
+/// LZ4-HC parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LZ4HCParams {
+    /// Compression level, 1 to 12 (best)
+    pub level: i32,
+}
 
-    pub fn best() -> Self {
-        Self(9)
+impl LZ4HCParams {
+    /// LZ4-HC at `level`
+    pub fn new(level: i32) -> Self {
+        Self { level }
     }
 
-    pub fn value(&self) -> u8 {
-        self.0
+    /// Check the level is in range
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=12).contains(&self.level) {
+            return Err(VdsError::Configuration(format!(
+                "LZ4-HC level {} is outside 1-12",
+                self.level
+            )));
+        }
+        Ok(())
     }
 }
 
-impl Default for CompressionLevel {
+impl Default for LZ4HCParams {
     fn default() -> Self {
-        Self(6)
+        Self { level: 9 }
     }
 }
 
 /// Trait for compression/decompression operations
 pub trait Compressor: Send + Sync {
-    /// Compress data
-    fn compress(&self, data: &[u8], level: CompressionLevel) -> Result<Vec<u8>>;
+    /// Compress data with the compressor's parameters
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>>;
 
     /// Decompress data
     fn decompress(&self, data: &[u8], expected_size: Option<usize>) -> Result<Vec<u8>>;
 
+    /// Decompress data into `output`, which must be exactly the uncompressed size
+    ///
+    /// Codecs that can decode in place override this to avoid an intermediate allocation.
+    fn decompress_into(&self, data: &[u8], output: &mut [u8]) -> Result<()> {
+        let decompressed = self.decompress(data, Some(output.len()))?;
+        check_decompressed_size(decompressed.len(), output.len())?;
+        output.copy_from_slice(&decompressed);
+        Ok(())
+    }
+
+    /// Decompress a shared buffer
+    ///
+    /// Codecs that store data verbatim return a view of `data` without copying.
+    fn decompress_bytes(&self, data: Bytes, expected_size: Option<usize>) -> Result<Bytes> {
+        self.decompress(&data, expected_size).map(Bytes::from)
+    }
+
     /// Get the compression method
     fn method(&self) -> CompressionMethod;
 }
 
+fn check_decompressed_size(size: usize, expected: usize) -> Result<()> {
+    if size != expected {
+        return Err(VdsError::Decompression(format!(
+            "Decompressed {} bytes, expected {}",
+            size, expected
+        )));
+    }
+    Ok(())
+}
+
 /// No compression
 #[derive(Debug, Default)]
 pub struct NoneCompressor;
 
 impl Compressor for NoneCompressor {
-    fn compress(&self, data: &[u8], _level: CompressionLevel) -> Result<Vec<u8>> {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
         Ok(data.to_vec())
     }
 
@@ -93,6 +421,16 @@ impl Compressor for NoneCompressor {
         Ok(data.to_vec())
     }
 
+    fn decompress_into(&self, data: &[u8], output: &mut [u8]) -> Result<()> {
+        check_decompressed_size(data.len(), output.len())?;
+        output.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn decompress_bytes(&self, data: Bytes, _expected_size: Option<usize>) -> Result<Bytes> {
+        Ok(data)
+    }
+
     fn method(&self) -> CompressionMethod {
         CompressionMethod::None
     }
@@ -100,11 +438,21 @@ impl Compressor for NoneCompressor {
 
 /// Deflate compression
 #[derive(Debug, Default)]
-pub struct DeflateCompressor;
+pub struct DeflateCompressor {
+    params: DeflateParams,
+}
+
+impl DeflateCompressor {
+    /// Deflate with the given parameters
+    pub fn new(params: DeflateParams) -> Self {
+        Self { params }
+    }
+}
 
 impl Compressor for DeflateCompressor {
-    fn compress(&self, data: &[u8], level: CompressionLevel) -> Result<Vec<u8>> {
-        let mut encoder = ZlibEncoder::new(data, FlateCompression::new(level.value() as u32));
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.params.validate()?;
+        let mut encoder = ZlibEncoder::new(data, FlateCompression::new(self.params.level));
         let mut compressed = Vec::new();
         encoder
             .read_to_end(&mut compressed)
@@ -125,23 +473,129 @@ impl Compressor for DeflateCompressor {
         Ok(decompressed)
     }
 
+    fn decompress_into(&self, data: &[u8], output: &mut [u8]) -> Result<()> {
+        let mut decoder = ZlibDecoder::new(data);
+        let mut excess = [0u8; 1];
+        decoder
+            .read_exact(output)
+            .and_then(|_| decoder.read(&mut excess))
+            .map_err(|e| VdsError::Decompression(e.to_string()))
+            .and_then(|excess| match excess {
+                0 => Ok(()),
+                _ => Err(VdsError::Decompression(format!(
+                    "Deflate stream exceeds the expected {} bytes",
+                    output.len()
+                ))),
+            })
+    }
+
     fn method(&self) -> CompressionMethod {
         CompressionMethod::Deflate
     }
 }
 
-/// Zstandard compression
-#[derive(Debug, Default)]
-pub struct ZstdCompressor;
+/// Path of a volume's zstd dictionary, relative to the volume root
+pub const ZSTD_DICTIONARY_PATH: &str = "zstd.dict";
+
+/// Default maximum size of a trained zstd dictionary, as in the zstd CLI
+pub const DEFAULT_DICTIONARY_SIZE: usize = 110 * 1024;
+
+/// Train a zstd dictionary of at most `max_size` bytes from sample bricks
+///
+/// Zstd needs a reasonable number of samples (tens to hundreds) to train from.
+pub fn train_zstd_dictionary<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_size)
+        .map_err(|e| VdsError::Compression(format!("Dictionary training failed: {}", e)))
+}
+
+/// Zstandard compression, optionally with a trained dictionary
+///
+/// Dictionaries help small bricks, which have too little data to build up their own history.
+#[derive(Debug, Default, Clone)]
+pub struct ZstdCompressor {
+    params: ZstdParams,
+    dictionary: Option<Arc<Vec<u8>>>,
+}
+
+impl ZstdCompressor {
+    /// Zstandard with default parameters and no dictionary
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the compression parameters
+    pub fn with_params(mut self, params: ZstdParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Use `dictionary` for both compression and decompression
+    pub fn with_dictionary(mut self, dictionary: Arc<Vec<u8>>) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    fn dictionary(&self) -> &[u8] {
+        self.dictionary.as_deref().map_or(&[], |d| d.as_slice())
+    }
+}
 
 impl Compressor for ZstdCompressor {
-    fn compress(&self, data: &[u8], level: CompressionLevel) -> Result<Vec<u8>> {
-        zstd::encode_all(data, level.value() as i32)
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        use zstd::stream::raw::CParameter;
+
+        self.params.validate()?;
+        let mut compressor =
+            zstd::bulk::Compressor::with_dictionary(self.params.level, self.dictionary())
+                .map_err(|e| VdsError::Compression(e.to_string()))?;
+        if let Some(window_log) = self.params.window_log {
+            compressor
+                .set_parameter(CParameter::WindowLog(window_log))
+                .map_err(|e| VdsError::Compression(e.to_string()))?;
+        }
+        compressor
+            .set_parameter(CParameter::EnableLongDistanceMatching(
+                self.params.long_distance_matching,
+            ))
+            .and_then(|_| compressor.compress(data))
             .map_err(|e| VdsError::Compression(e.to_string()))
     }
 
-    fn decompress(&self, data: &[u8], _expected_size: Option<usize>) -> Result<Vec<u8>> {
-        zstd::decode_all(data).map_err(|e| VdsError::Decompression(e.to_string()))
+    fn decompress(&self, data: &[u8], expected_size: Option<usize>) -> Result<Vec<u8>> {
+        let decompressed = zstd::stream::read::Decoder::with_dictionary(data, self.dictionary())
+            .and_then(|mut decoder| {
+                // Accept any window size the encoder may have been configured with
+                decoder.window_log_max(ZSTD_WINDOW_LOG_MAX)?;
+                let mut decompressed = Vec::new();
+                match expected_size {
+                    // One byte past the expected size is enough to detect an oversized stream
+                    Some(size) => decoder
+                        .take(size as u64 + 1)
+                        .read_to_end(&mut decompressed)?,
+                    None => decoder.read_to_end(&mut decompressed)?,
+                };
+                Ok(decompressed)
+            })
+            .map_err(|e| VdsError::Decompression(e.to_string()))?;
+        match expected_size {
+            Some(size) if decompressed.len() > size => Err(VdsError::Decompression(format!(
+                "Zstd stream exceeds the expected {} bytes",
+                size
+            ))),
+            _ => Ok(decompressed),
+        }
+    }
+
+    fn decompress_into(&self, data: &[u8], output: &mut [u8]) -> Result<()> {
+        use zstd::stream::raw::DParameter;
+
+        let size = zstd::bulk::Decompressor::with_dictionary(self.dictionary())
+            .and_then(|mut decompressor| {
+                decompressor.set_parameter(DParameter::WindowLogMax(ZSTD_WINDOW_LOG_MAX))?;
+                decompressor.decompress_to_buffer(data, output)
+            })
+            .map_err(|e| VdsError::Decompression(e.to_string()))?;
+        check_decompressed_size(size, output.len())
     }
 
     fn method(&self) -> CompressionMethod {
@@ -149,6 +603,99 @@ impl Compressor for ZstdCompressor {
     }
 }
 
+/// LZ4 block compression, optionally in high-compression mode
+///
+/// Blocks are prefixed with their uncompressed size, so decompression needs no expected size.
+/// Both modes produce the same format.
+#[derive(Debug)]
+pub struct LZ4Compressor {
+    mode: LZ4Mode,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LZ4Mode {
+    Fast(LZ4Params),
+    HighCompression(LZ4HCParams),
+}
+
+impl LZ4Compressor {
+    /// Fast LZ4 compression with default parameters
+    pub fn new() -> Self {
+        Self::with_params(LZ4Params::default())
+    }
+
+    /// Fast LZ4 compression with the given parameters
+    pub fn with_params(params: LZ4Params) -> Self {
+        Self {
+            mode: LZ4Mode::Fast(params),
+        }
+    }
+
+    /// LZ4-HC compression with default parameters
+    pub fn high_compression() -> Self {
+        Self::with_hc_params(LZ4HCParams::default())
+    }
+
+    /// LZ4-HC compression with the given parameters
+    pub fn with_hc_params(params: LZ4HCParams) -> Self {
+        Self {
+            mode: LZ4Mode::HighCompression(params),
+        }
+    }
+}
+
+impl Default for LZ4Compressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compressor for LZ4Compressor {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mode = match self.mode {
+            LZ4Mode::Fast(params) => {
+                params.validate()?;
+                lz4::block::CompressionMode::FAST(params.acceleration)
+            }
+            LZ4Mode::HighCompression(params) => {
+                params.validate()?;
+                lz4::block::CompressionMode::HIGHCOMPRESSION(params.level)
+            }
+        };
+        lz4::block::compress(data, Some(mode), true)
+            .map_err(|e| VdsError::Compression(e.to_string()))
+    }
+
+    fn decompress(&self, data: &[u8], expected_size: Option<usize>) -> Result<Vec<u8>> {
+        let prefix: [u8; 4] = data
+            .get(..4)
+            .and_then(|prefix| prefix.try_into().ok())
+            .ok_or_else(|| {
+                VdsError::Decompression("LZ4 block is missing its size prefix".to_string())
+            })?;
+        let size = u32::from_le_bytes(prefix) as usize;
+        if let Some(expected) = expected_size {
+            check_decompressed_size(size, expected)?;
+        }
+        let mut decompressed = vec![0u8; size];
+        self.decompress_into(data, &mut decompressed)?;
+        Ok(decompressed)
+    }
+
+    fn decompress_into(&self, data: &[u8], output: &mut [u8]) -> Result<()> {
+        let size = lz4::block::decompress_to_buffer(data, None, output)
+            .map_err(|e| VdsError::Decompression(e.to_string()))?;
+        check_decompressed_size(size, output.len())
+    }
+
+    fn method(&self) -> CompressionMethod {
+        match self.mode {
+            LZ4Mode::Fast(_) => CompressionMethod::LZ4,
+            LZ4Mode::HighCompression(_) => CompressionMethod::LZ4HC,
+        }
+    }
+}
+
 /// Run-length encoding compressor
 #[derive(Debug, Default)]
 pub struct RLECompressor;
@@ -201,7 +748,7 @@ impl RLECompressor {
 }
 
 impl Compressor for RLECompressor {
-    fn compress(&self, data: &[u8], _level: CompressionLevel) -> Result<Vec<u8>> {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
         Ok(Self::compress_internal(data))
     }
 
@@ -214,20 +761,483 @@ impl Compressor for RLECompressor {
     }
 }
 
-/// Get a compressor for a given method
-pub fn get_compressor(method: CompressionMethod) -> Box<dyn Compressor> {
+/// Run-length encoding over multi-byte elements
+///
+/// Runs of identical elements are stored as a varint run length followed by the element, so a
+/// constant brick of any size and data type compresses to a few bytes. The stream starts with
+/// the element size and any trailing bytes that do not form a whole element, so decompression
+/// needs no parameters.
+#[derive(Debug)]
+pub struct ElementRLECompressor {
+    element_size: usize,
+}
+
+impl ElementRLECompressor {
+    /// Run-length encode elements of `element_size` bytes (1-255)
+    pub fn new(element_size: usize) -> Self {
+        Self {
+            element_size: element_size.clamp(1, u8::MAX as usize),
+        }
+    }
+}
+
+impl Default for ElementRLECompressor {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// Largest output decoded from an element RLE stream when the expected size is unknown
+const MAX_ELEMENT_RLE_BYTES: usize = 1 << 30;
+
+impl Compressor for ElementRLECompressor {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let size = self.element_size;
+        let whole = data.len() / size * size;
+        let tail = &data[whole..];
+
+        let mut compressed = vec![size as u8, tail.len() as u8];
+        compressed.extend_from_slice(tail);
+        let mut elements = data[..whole].chunks_exact(size).peekable();
+        while let Some(element) = elements.next() {
+            let mut run = 1u64;
+            while elements.next_if_eq(&element).is_some() {
+                run += 1;
+            }
+            write_varint(&mut compressed, run);
+            compressed.extend_from_slice(element);
+        }
+        Ok(compressed)
+    }
+
+    fn decompress(&self, data: &[u8], expected_size: Option<usize>) -> Result<Vec<u8>> {
+        let truncated = || VdsError::Decompression("Truncated element RLE stream".to_string());
+        let (size, tail_len) = match data {
+            [size, tail_len, ..] if *size > 0 => (*size as usize, *tail_len as usize),
+            _ => return Err(truncated()),
+        };
+        let tail = data.get(2..2 + tail_len).ok_or_else(truncated)?;
+
+        let limit = expected_size.unwrap_or(MAX_ELEMENT_RLE_BYTES);
+        let too_long =
+            || VdsError::Decompression(format!("Element RLE stream exceeds {} bytes", limit));
+
+        let mut decompressed = Vec::with_capacity(expected_size.unwrap_or(0));
+        let mut position = 2 + tail_len;
+        while position < data.len() {
+            let run = read_varint(data, &mut position).ok_or_else(truncated)?;
+            let element = data.get(position..position + size).ok_or_else(truncated)?;
+            position += size;
+            usize::try_from(run)
+                .ok()
+                .and_then(|run| run.checked_mul(size))
+                .and_then(|bytes| bytes.checked_add(decompressed.len()))
+                .filter(|&total| total <= limit.saturating_sub(tail_len))
+                .ok_or_else(too_long)?;
+            for _ in 0..run {
+                decompressed.extend_from_slice(element);
+            }
+        }
+        decompressed.extend_from_slice(tail);
+        Ok(decompressed)
+    }
+
+    fn method(&self) -> CompressionMethod {
+        CompressionMethod::ElementRLE
+    }
+}
+
+/// Whether `data` consists of one element of `element_size` bytes repeated
+pub fn is_constant(data: &[u8], element_size: usize) -> bool {
+    element_size > 0
+        && !data.is_empty()
+        && data.len() % element_size == 0
+        && data
+            .chunks_exact(element_size)
+            .all(|element| element == &data[..element_size])
+}
+
+/// Constant bricks stored as their single element, as OpenVDS does for constant chunks
+///
+/// Compression fails unless every element is equal. Decompression repeats the stored element
+/// and needs the expected size.
+#[derive(Debug)]
+pub struct ConstantCompressor {
+    element_size: usize,
+}
+
+impl ConstantCompressor {
+    /// Constant bricks of elements of `element_size` bytes
+    pub fn new(element_size: usize) -> Self {
+        Self {
+            element_size: element_size.max(1),
+        }
+    }
+}
+
+impl Default for ConstantCompressor {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Compressor for ConstantCompressor {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if !is_constant(data, self.element_size) {
+            return Err(VdsError::Compression(
+                "Brick elements are not all equal".to_string(),
+            ));
+        }
+        Ok(data[..self.element_size].to_vec())
+    }
+
+    fn decompress(&self, data: &[u8], expected_size: Option<usize>) -> Result<Vec<u8>> {
+        let expected = expected_size.ok_or_else(|| {
+            VdsError::Decompression("Constant bricks need the expected size".to_string())
+        })?;
+        if data.is_empty() || expected % data.len() != 0 {
+            return Err(VdsError::Decompression(format!(
+                "A {}-byte element does not fill {} bytes",
+                data.len(),
+                expected
+            )));
+        }
+        Ok(data.repeat(expected / data.len()))
+    }
+
+    fn decompress_into(&self, data: &[u8], output: &mut [u8]) -> Result<()> {
+        if data.is_empty() || output.len() % data.len() != 0 {
+            return Err(VdsError::Decompression(format!(
+                "A {}-byte element does not fill {} bytes",
+                data.len(),
+                output.len()
+            )));
+        }
+        for element in output.chunks_exact_mut(data.len()) {
+            element.copy_from_slice(data);
+        }
+        Ok(())
+    }
+
+    fn method(&self) -> CompressionMethod {
+        CompressionMethod::Constant
+    }
+}
+
+/// Stream tag of a losslessly stored brick
+const QUANTIZED_LOSSLESS: u8 = 0;
+/// Stream tag of a quantized F32 brick
+const QUANTIZED_F32: u8 = 1;
+/// Bytes of the quantized stream header: tag, step, offset, sample count
+const QUANTIZED_HEADER_SIZE: usize = 1 + 8 + 8 + 4;
+
+/// Error-bounded lossy compression of F32 samples
+///
+/// Samples are quantized to multiples of twice the maximum error from the brick minimum, and the
+/// differences between consecutive quantized values are varint-coded and compressed with Zstandard.
+/// Every decoded sample is within the maximum error of the original; non-finite samples and any
+/// sample the quantization cannot reproduce are stored exactly. Without a maximum error the
+/// brick is stored losslessly with Zstandard. Streams are self-describing, so decompression needs
+/// no parameters.
+#[derive(Debug, Default)]
+pub struct QuantizedCompressor {
+    max_error: Option<f64>,
+    zstd: ZstdCompressor,
+}
+
+impl QuantizedCompressor {
+    /// Quantize F32 samples with the given maximum absolute error
+    pub fn new(max_error: f64) -> Self {
+        Self {
+            max_error: (max_error > 0.0 && max_error.is_finite()).then_some(max_error),
+            zstd: ZstdCompressor::new(),
+        }
+    }
+
+    /// Store bricks losslessly; used for non-F32 data or when lossy compression is not allowed
+    pub fn lossless() -> Self {
+        Self::default()
+    }
+
+    /// Set the parameters of the Zstandard stage
+    pub fn with_zstd_params(mut self, params: ZstdParams) -> Self {
+        self.zstd = self.zstd.with_params(params);
+        self
+    }
+
+    fn quantize(&self, data: &[u8], max_error: f64) -> Result<Vec<u8>> {
+        if data.len() % 4 != 0 {
+            return Err(VdsError::Compression(
+                "Quantized compression requires F32 samples".to_string(),
+            ));
+        }
+        let values: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|c| f32::from_ne_bytes(c.try_into().unwrap()))
+            .collect();
+        let step = 2.0 * max_error;
+        let offset = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold(f64::INFINITY, |min, &v| min.min(v as f64));
+        let offset = if offset.is_finite() { offset } else { 0.0 };
+
+        let mut residuals = Vec::new();
+        let mut exceptions = Vec::new();
+        let mut previous = 0i64;
+        for (i, &value) in values.iter().enumerate() {
+            let q = ((value as f64 - offset) / step).round();
+            let exact = value.is_finite()
+                && q.abs() < (1u64 << 52) as f64
+                && ((offset + q * step) as f32 as f64 - value as f64).abs() <= max_error;
+            if !exact {
+                exceptions.push((i, value));
+                write_varint(&mut residuals, 0);
+                continue;
+            }
+            let q = q as i64;
+            write_varint(&mut residuals, zigzag(q - previous));
+            previous = q;
+        }
+
+        let mut stream = Vec::new();
+        write_varint(&mut stream, exceptions.len() as u64);
+        let mut last = 0;
+        for &(index, value) in &exceptions {
+            write_varint(&mut stream, (index - last) as u64);
+            stream.extend_from_slice(&value.to_bits().to_le_bytes());
+            last = index;
+        }
+        stream.extend_from_slice(&residuals);
+
+        let mut compressed = Vec::with_capacity(QUANTIZED_HEADER_SIZE + stream.len() / 2);
+        compressed.push(QUANTIZED_F32);
+        compressed.extend_from_slice(&step.to_le_bytes());
+        compressed.extend_from_slice(&offset.to_le_bytes());
+        compressed.extend_from_slice(&(values.len() as u32).to_le_bytes());
+        compressed.extend(self.zstd.compress(&stream)?);
+        Ok(compressed)
+    }
+
+    fn dequantize(data: &[u8], expected_size: Option<usize>) -> Result<Vec<u8>> {
+        let truncated = || VdsError::Decompression("Truncated quantized stream".to_string());
+        let header = data.get(..QUANTIZED_HEADER_SIZE).ok_or_else(truncated)?;
+        let step = f64::from_le_bytes(header[1..9].try_into().unwrap());
+        let offset = f64::from_le_bytes(header[9..17].try_into().unwrap());
+        let count = u32::from_le_bytes(header[17..21].try_into().unwrap()) as usize;
+        if expected_size.is_some_and(|size| size != count * 4) {
+            return Err(VdsError::Decompression(format!(
+                "Quantized stream holds {} values, expected {} bytes",
+                count,
+                expected_size.unwrap_or_default()
+            )));
+        }
+        let stream = ZstdCompressor::new().decompress(&data[QUANTIZED_HEADER_SIZE..], None)?;
+
+        // Every value takes at least one varint byte
+        if count > stream.len() {
+            return Err(truncated());
+        }
+
+        let mut position = 0;
+        let exception_count = read_varint(&stream, &mut position).ok_or_else(truncated)?;
+        let mut exceptions = Vec::new();
+        let mut index = 0;
+        for _ in 0..exception_count {
+            index += read_varint(&stream, &mut position).ok_or_else(truncated)? as usize;
+            let bits = stream.get(position..position + 4).ok_or_else(truncated)?;
+            exceptions.push((
+                index,
+                f32::from_bits(u32::from_le_bytes(bits.try_into().unwrap())),
+            ));
+            position += 4;
+        }
+
+        let mut values = Vec::with_capacity(count);
+        let mut q = 0i64;
+        for _ in 0..count {
+            q += unzigzag(read_varint(&stream, &mut position).ok_or_else(truncated)?);
+            values.push((offset + q as f64 * step) as f32);
+        }
+        for (index, value) in exceptions {
+            *values.get_mut(index).ok_or_else(truncated)? = value;
+        }
+        Ok(values.iter().flat_map(|v| v.to_ne_bytes()).collect())
+    }
+}
+
+impl Compressor for QuantizedCompressor {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self.max_error {
+            Some(max_error) => self.quantize(data, max_error),
+            None => {
+                let mut compressed = vec![QUANTIZED_LOSSLESS];
+                compressed.extend(self.zstd.compress(data)?);
+                Ok(compressed)
+            }
+        }
+    }
+
+    fn decompress(&self, data: &[u8], expected_size: Option<usize>) -> Result<Vec<u8>> {
+        match data.first() {
+            Some(&QUANTIZED_LOSSLESS) => {
+                ZstdCompressor::new().decompress(&data[1..], expected_size)
+            }
+            Some(&QUANTIZED_F32) => Self::dequantize(data, expected_size),
+            Some(tag) => Err(VdsError::Decompression(format!(
+                "Unknown quantized stream tag {}",
+                tag
+            ))),
+            None => Err(VdsError::Decompression(
+                "Empty quantized stream".to_string(),
+            )),
+        }
+    }
+
+    fn method(&self) -> CompressionMethod {
+        CompressionMethod::Quantized
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Append `value` as an LEB128 varint
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Read an LEB128 varint at `position`, advancing it
+fn read_varint(data: &[u8], position: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*position)?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Creates a default-configured compressor for a codec
+pub type CompressorFactory = Arc<dyn Fn() -> Box<dyn Compressor> + Send + Sync>;
+
+/// Maps codec ids to compressor factories
+///
+/// Built-in codecs are pre-registered; their volume-specific configuration (parameters,
+/// element size, dictionary) is applied by
+/// [`VdsMetadata::compressor_for`](crate::metadata::VdsMetadata::compressor_for). Further codecs
+/// are registered under ids from [`FIRST_CUSTOM_CODEC_ID`] up and used as
+/// [`CompressionMethod::Custom`].
+pub struct CompressorRegistry {
+    factories: RwLock<HashMap<u8, CompressorFactory>>,
+}
+
+impl CompressorRegistry {
+    /// A registry holding the built-in codecs
+    pub fn new() -> Self {
+        let registry = Self {
+            factories: RwLock::new(HashMap::new()),
+        };
+        {
+            let mut factories = registry.factories.write();
+            for method in CompressionMethod::BUILT_IN {
+                factories.insert(method.id(), Arc::new(move || built_in_compressor(method)));
+            }
+        }
+        registry
+    }
+
+    /// The process-wide registry used when reading and writing volumes
+    pub fn global() -> &'static Self {
+        static REGISTRY: OnceLock<CompressorRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(Self::new)
+    }
+
+    /// Register `factory` for the codec `id`, replacing any codec previously registered for it
+    ///
+    /// The compressors it creates should report [`CompressionMethod::Custom`] with this id.
+    pub fn register(
+        &self,
+        id: u8,
+        factory: impl Fn() -> Box<dyn Compressor> + Send + Sync + 'static,
+    ) -> Result<()> {
+        if id < FIRST_CUSTOM_CODEC_ID {
+            return Err(VdsError::Configuration(format!(
+                "Codec id {} is reserved for built-in codecs; custom codecs use {} and up",
+                id, FIRST_CUSTOM_CODEC_ID
+            )));
+        }
+        self.factories.write().insert(id, Arc::new(factory));
+        Ok(())
+    }
+
+    /// Whether a codec is registered for `method`
+    pub fn is_registered(&self, method: CompressionMethod) -> bool {
+        self.factories.read().contains_key(&method.id())
+    }
+
+    /// Create a compressor for `method`
+    pub fn create(&self, method: CompressionMethod) -> Result<Box<dyn Compressor>> {
+        let factory = self
+            .factories
+            .read()
+            .get(&method.id())
+            .cloned()
+            .ok_or(VdsError::UnregisteredCodec(method.id()))?;
+        Ok(factory())
+    }
+}
+
+impl Default for CompressorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn built_in_compressor(method: CompressionMethod) -> Box<dyn Compressor> {
     match method {
         CompressionMethod::None => Box::new(NoneCompressor),
-        CompressionMethod::Deflate => Box::new(DeflateCompressor),
+        CompressionMethod::Deflate => Box::new(DeflateCompressor::default()),
         CompressionMethod::RLE => Box::new(RLECompressor),
-        CompressionMethod::Zstd => Box::new(ZstdCompressor),
+        CompressionMethod::Zstd => Box::new(ZstdCompressor::new()),
         CompressionMethod::Wavelet => {
             // Placeholder - would need to implement Bluware's wavelet algorithm
             Box::new(NoneCompressor)
         }
+        CompressionMethod::Quantized => Box::new(QuantizedCompressor::lossless()),
+        CompressionMethod::ElementRLE => Box::new(ElementRLECompressor::default()),
+        CompressionMethod::LZ4 => Box::new(LZ4Compressor::new()),
+        CompressionMethod::LZ4HC => Box::new(LZ4Compressor::high_compression()),
+        CompressionMethod::Constant => Box::new(ConstantCompressor::default()),
+        CompressionMethod::Custom(_) => unreachable!("custom codecs are not built in"),
     }
 }
 
+/// Register a custom codec in the [global](CompressorRegistry::global) registry
+pub fn register_compressor(
+    id: u8,
+    factory: impl Fn() -> Box<dyn Compressor> + Send + Sync + 'static,
+) -> Result<()> {
+    CompressorRegistry::global().register(id, factory)
+}
+
+/// Get a compressor for a given method from the [global](CompressorRegistry::global) registry
+pub fn get_compressor(method: CompressionMethod) -> Result<Box<dyn Compressor>> {
+    CompressorRegistry::global().create(method)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -236,9 +1246,7 @@ mod tests {
     fn test_no_compression() {
         let compressor = NoneCompressor;
         let data = b"Hello, world!";
-        let compressed = compressor
-            .compress(data, CompressionLevel::default())
-            .unwrap();
+        let compressed = compressor.compress(data).unwrap();
         assert_eq!(compressed, data);
         let decompressed = compressor.decompress(&compressed, None).unwrap();
         assert_eq!(decompressed, data);
@@ -246,11 +1254,9 @@ mod tests {
 
     #[test]
     fn test_deflate() {
-        let compressor = DeflateCompressor;
+        let compressor = DeflateCompressor::default();
         let data = b"Hello, world! ".repeat(100);
-        let compressed = compressor
-            .compress(&data, CompressionLevel::default())
-            .unwrap();
+        let compressed = compressor.compress(&data).unwrap();
         assert!(compressed.len() < data.len());
         let decompressed = compressor
             .decompress(&compressed, Some(data.len()))
@@ -260,36 +1266,375 @@ mod tests {
 
     #[test]
     fn test_zstd() {
-        let compressor = ZstdCompressor;
+        let compressor = ZstdCompressor::new();
         let data = b"Hello, world! ".repeat(100);
-        let compressed = compressor
-            .compress(&data, CompressionLevel::default())
-            .unwrap();
+        let compressed = compressor.compress(&data).unwrap();
         assert!(compressed.len() < data.len());
         let decompressed = compressor.decompress(&compressed, None).unwrap();
         assert_eq!(decompressed, data);
+
+        // Output beyond the expected size is rejected rather than read to the end
+        let decompressed = compressor
+            .decompress(&compressed, Some(data.len()))
+            .unwrap();
+        assert_eq!(decompressed, data);
+        assert!(compressor
+            .decompress(&compressed, Some(data.len() - 1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_zstd_dictionary() {
+        // Small bricks sharing structure but not content
+        let bricks: Vec<Vec<u8>> = (0..200u32)
+            .map(|b| {
+                (0..256u32)
+                    .flat_map(|i| (((i * 7 + b) % 64) as f32 * 0.5 - 16.0).to_ne_bytes())
+                    .collect()
+            })
+            .collect();
+        let dictionary = Arc::new(train_zstd_dictionary(&bricks[..150], 8 * 1024).unwrap());
+
+        let with = ZstdCompressor::new().with_dictionary(dictionary);
+        let without = ZstdCompressor::new();
+        let (mut sized_with, mut sized_without) = (0, 0);
+        for brick in &bricks[150..] {
+            let compressed = with.compress(brick).unwrap();
+            assert_eq!(&with.decompress(&compressed, None).unwrap(), brick);
+            sized_with += compressed.len();
+            sized_without += without.compress(brick).unwrap().len();
+        }
+        assert!(sized_with * 2 < sized_without);
+        assert!(train_zstd_dictionary(&bricks[..1], 1024).is_err());
+    }
+
+    #[test]
+    fn test_codec_params() {
+        let data: Vec<u8> = (0..50000u32)
+            .flat_map(|i| ((i % 997) as f32 * 0.25).to_ne_bytes())
+            .collect();
+        for params in [
+            ZstdParams::new(19),
+            ZstdParams::new(-5),
+            ZstdParams::new(3)
+                .with_window_log(24)
+                .with_long_distance_matching(true),
+        ] {
+            let compressor = ZstdCompressor::new().with_params(params);
+            let compressed = compressor.compress(&data).unwrap();
+            assert_eq!(
+                ZstdCompressor::new().decompress(&compressed, None).unwrap(),
+                data
+            );
+        }
+        let deflate = DeflateCompressor::new(DeflateParams::new(1));
+        let compressed = deflate.compress(&data).unwrap();
+        assert_eq!(deflate.decompress(&compressed, None).unwrap(), data);
+
+        assert!(ZstdCompressor::new()
+            .with_params(ZstdParams::new(23))
+            .compress(&data)
+            .is_err());
+        assert!(ZstdParams::new(3).with_window_log(9).validate().is_err());
+        assert!(DeflateParams::new(10).validate().is_err());
+        assert!(LZ4Compressor::with_params(LZ4Params::new(0))
+            .compress(&data)
+            .is_err());
+        assert!(LZ4HCParams::new(13).validate().is_err());
+
+        // Parameters not given in stored metadata take their defaults
+        let params: CompressionParams = serde_json::from_str(r#"{"zstd": {"level": 22}}"#).unwrap();
+        assert_eq!(params.zstd, ZstdParams::new(22));
+        assert_eq!(params.deflate, DeflateParams::default());
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CompressionParams>(&json).unwrap(),
+            params
+        );
+    }
+
+    #[test]
+    fn test_constant_and_adaptive() {
+        let constant: Vec<u8> = (0..100).flat_map(|_| 2.5f32.to_ne_bytes()).collect();
+        let compressor = ConstantCompressor::new(4);
+        let compressed = compressor.compress(&constant).unwrap();
+        assert_eq!(compressed, 2.5f32.to_ne_bytes());
+        assert_eq!(
+            compressor.decompress(&compressed, Some(400)).unwrap(),
+            constant
+        );
+        assert!(compressor.decompress(&compressed, None).is_err());
+        assert!(compressor.decompress(&compressed, Some(402)).is_err());
+        assert!(compressor.compress(&[1, 0, 0, 0, 2, 0, 0, 0]).is_err());
+        assert!(!is_constant(&[1, 1, 1], 2));
+
+        let data: Vec<u8> = (0..4096u32)
+            .flat_map(|i| ((i as f32 * 0.01).sin() * 100.0).round().to_ne_bytes())
+            .collect();
+        let candidates = vec![
+            CompressionMethod::None,
+            CompressionMethod::LZ4,
+            CompressionMethod::Zstd,
+        ];
+        let sizes: Vec<usize> = candidates
+            .iter()
+            .map(|&m| get_compressor(m).unwrap().compress(&data).unwrap().len())
+            .collect();
+
+        let (method, compressed) = AdaptiveCompression::smallest(candidates.clone())
+            .compress(&data, get_compressor)
+            .unwrap();
+        assert_eq!(method, CompressionMethod::Zstd);
+        assert_eq!(compressed.len(), *sizes.iter().min().unwrap());
+
+        // LZ4 is listed before Zstd, so it wins once its size is within the budget
+        let ratio = sizes[1] as f64 / sizes[2] as f64;
+        let within = AdaptiveCompression::fastest_within(candidates.clone(), ratio + 0.01);
+        assert_eq!(
+            within.compress(&data, get_compressor).unwrap().0,
+            CompressionMethod::LZ4
+        );
+        let tight = AdaptiveCompression::fastest_within(candidates, 1.0);
+        assert_eq!(
+            tight.compress(&data, get_compressor).unwrap().0,
+            CompressionMethod::Zstd
+        );
+        assert!(AdaptiveCompression::smallest(vec![]).validate().is_err());
+        assert!(
+            AdaptiveCompression::fastest_within(vec![CompressionMethod::LZ4], 0.5)
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_decompress_into() {
+        let data: Vec<u8> = (0..3000u32)
+            .flat_map(|i| ((i / 3) as u16).to_ne_bytes())
+            .collect();
+        let compressors: Vec<Box<dyn Compressor>> = vec![
+            Box::new(NoneCompressor),
+            Box::new(DeflateCompressor::default()),
+            Box::new(RLECompressor),
+            Box::new(ZstdCompressor::new()),
+            Box::new(LZ4Compressor::new()),
+            Box::new(ElementRLECompressor::new(2)),
+            Box::new(QuantizedCompressor::lossless()),
+        ];
+        for compressor in compressors {
+            let compressed = compressor.compress(&data).unwrap();
+            let mut output = vec![0u8; data.len()];
+            compressor
+                .decompress_into(&compressed, &mut output)
+                .unwrap();
+            assert_eq!(output, data, "{:?}", compressor.method());
+
+            let mut short = vec![0u8; data.len() - 2];
+            let mut long = vec![0u8; data.len() + 2];
+            assert!(compressor.decompress_into(&compressed, &mut short).is_err());
+            assert!(compressor.decompress_into(&compressed, &mut long).is_err());
+        }
+
+        let mut output = [0u8; 12];
+        ConstantCompressor::new(4)
+            .decompress_into(&[1, 2, 3, 4], &mut output)
+            .unwrap();
+        assert_eq!(output, [1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4]);
+
+        // Uncompressed data is shared, not copied
+        let stored = Bytes::from(data.clone());
+        let shared = NoneCompressor
+            .decompress_bytes(stored.clone(), Some(data.len()))
+            .unwrap();
+        assert_eq!(shared.as_ptr(), stored.as_ptr());
+    }
+
+    /// Stores bytes reversed, standing in for an in-house codec
+    struct ReverseCompressor;
+
+    impl Compressor for ReverseCompressor {
+        fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data.iter().rev().copied().collect())
+        }
+
+        fn decompress(&self, data: &[u8], _expected_size: Option<usize>) -> Result<Vec<u8>> {
+            self.compress(data)
+        }
+
+        fn method(&self) -> CompressionMethod {
+            CompressionMethod::Custom(200)
+        }
+    }
+
+    #[test]
+    fn test_compressor_registry() {
+        for method in CompressionMethod::BUILT_IN {
+            assert_eq!(CompressionMethod::from_u8(method.id()), Some(method));
+        }
+        assert_eq!(
+            CompressionMethod::from_u8(200),
+            Some(CompressionMethod::Custom(200))
+        );
+        assert_eq!(CompressionMethod::from_u8(100), None);
+
+        let registry = CompressorRegistry::new();
+        let custom = CompressionMethod::Custom(200);
+        assert!(registry.is_registered(CompressionMethod::LZ4));
+        assert!(!registry.is_registered(custom));
+        match registry.create(custom) {
+            Err(e @ VdsError::UnregisteredCodec(200)) => {
+                assert_eq!(e.to_string(), "No compressor registered for codec id 200")
+            }
+            other => panic!("unexpected {:?}", other.map(|c| c.method())),
+        }
+
+        registry
+            .register(200, || Box::new(ReverseCompressor))
+            .unwrap();
+        let compressor = registry.create(custom).unwrap();
+        assert_eq!(compressor.compress(&[1, 2, 3]).unwrap(), vec![3, 2, 1]);
+        assert!(registry
+            .register(CompressionMethod::Zstd.id(), || Box::new(ReverseCompressor))
+            .is_err());
     }
 
     #[test]
     fn test_rle() {
         let compressor = RLECompressor;
         let data = vec![1u8; 100];
-        let compressed = compressor
-            .compress(&data, CompressionLevel::default())
-            .unwrap();
+        let compressed = compressor.compress(&data).unwrap();
         assert!(compressed.len() < data.len());
         let decompressed = compressor.decompress(&compressed, None).unwrap();
         assert_eq!(decompressed, data);
     }
 
+    #[test]
+    fn test_lz4() {
+        let data: Vec<u8> = (0..20000u32)
+            .flat_map(|i| ((i as f32 * 0.01).sin() * 100.0).round().to_ne_bytes())
+            .collect();
+        let fast = get_compressor(CompressionMethod::LZ4)
+            .unwrap()
+            .compress(&data)
+            .unwrap();
+        let high = LZ4Compressor::with_hc_params(LZ4HCParams::new(12))
+            .compress(&data)
+            .unwrap();
+        assert!(high.len() <= fast.len() && fast.len() < data.len());
+
+        // Both modes share one block format
+        for compressed in [&fast, &high] {
+            let decompressed = LZ4Compressor::new().decompress(compressed, None).unwrap();
+            assert_eq!(decompressed, data);
+        }
+        assert!(LZ4Compressor::new().decompress(&fast[..10], None).is_err());
+
+        // The size prefix must match the expected size and the block contents
+        let lz4 = LZ4Compressor::new();
+        assert_eq!(lz4.decompress(&fast, Some(data.len())).unwrap(), data);
+        assert!(lz4.decompress(&fast, Some(data.len() - 1)).is_err());
+        let mut damaged = fast.clone();
+        damaged[..4].copy_from_slice(&(data.len() as u32 - 4).to_le_bytes());
+        assert!(lz4.decompress(&damaged, None).is_err());
+        assert!(lz4.decompress(&fast[..3], None).is_err());
+    }
+
+    #[test]
+    fn test_element_rle() {
+        // A constant F32 brick of 64^3 samples
+        let data = 1.5f32.to_ne_bytes().repeat(64 * 64 * 64);
+        let compressor = ElementRLECompressor::new(4);
+        let compressed = compressor.compress(&data).unwrap();
+        assert!(compressed.len() <= 10);
+        let decompressed = get_compressor(CompressionMethod::ElementRLE)
+            .unwrap()
+            .decompress(&compressed, Some(data.len()))
+            .unwrap();
+        assert_eq!(decompressed, data);
+
+        // Mixed runs with a partial trailing element
+        let mut data: Vec<u8> = [0u16; 300]
+            .iter()
+            .chain(&[7, 7, 9])
+            .flat_map(|v| v.to_ne_bytes())
+            .collect();
+        data.push(42);
+        let compressor = ElementRLECompressor::new(2);
+        let compressed = compressor.compress(&data).unwrap();
+        assert_eq!(compressor.decompress(&compressed, None).unwrap(), data);
+        assert!(compressor.decompress(&compressed, Some(10)).is_err());
+        assert!(compressor
+            .decompress(&compressed[..compressed.len() - 1], None)
+            .is_err());
+
+        // Crafted run lengths that would wrap or exhaust memory
+        for run in [u64::MAX, u64::MAX / 2, MAX_ELEMENT_RLE_BYTES as u64] {
+            let mut crafted = vec![4, 0];
+            write_varint(&mut crafted, run);
+            crafted.extend_from_slice(&[1, 2, 3, 4]);
+            assert!(compressor.decompress(&crafted, None).is_err());
+            assert!(compressor.decompress(&crafted, Some(16)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_quantized_error_bound() {
+        let mut values: Vec<f32> = (0..4096)
+            .map(|i| 1000.0 * (i as f32 * 0.01).sin() + 0.37 * (i % 7) as f32)
+            .collect();
+        values[10] = f32::NAN;
+        values[11] = f32::NEG_INFINITY;
+        values[12] = 3.0e12;
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+
+        let max_error = 0.5;
+        let compressor = QuantizedCompressor::new(max_error);
+        let compressed = compressor.compress(&data).unwrap();
+        assert!(compressed.len() * 4 < data.len());
+
+        // Decoding needs no parameters
+        let decompressed = get_compressor(CompressionMethod::Quantized)
+            .unwrap()
+            .decompress(&compressed, None)
+            .unwrap();
+        let decoded: Vec<f32> = decompressed
+            .chunks_exact(4)
+            .map(|c| f32::from_ne_bytes(c.try_into().unwrap()))
+            .collect();
+        assert_eq!(decoded.len(), values.len());
+        assert!(decoded[10].is_nan());
+        assert_eq!((decoded[11], decoded[12]), (f32::NEG_INFINITY, 3.0e12));
+        for (a, b) in values.iter().zip(&decoded).filter(|(a, _)| a.is_finite()) {
+            assert!((*a as f64 - *b as f64).abs() <= max_error);
+        }
+
+        // A damaged value count is rejected before allocating for it
+        let mut damaged = compressed.clone();
+        damaged[17..21].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(compressor.decompress(&damaged, None).is_err());
+        assert!(compressor.decompress(&damaged, Some(data.len())).is_err());
+        assert!(compressor
+            .decompress(&compressed, Some(data.len() - 4))
+            .is_err());
+    }
+
+    #[test]
+    fn test_quantized_lossless() {
+        let data: Vec<u8> = (0..1000u32)
+            .flat_map(|i| (i as f32).sqrt().to_ne_bytes())
+            .collect();
+        let compressor = QuantizedCompressor::lossless();
+        let compressed = compressor.compress(&data).unwrap();
+        assert_eq!(compressor.decompress(&compressed, None).unwrap(), data);
+        assert!(compressor.decompress(&[9, 1, 2], None).is_err());
+    }
+
     #[test]
     fn test_rle_mixed() {
         let compressor = RLECompressor;
         let mut data = vec![1u8; 50];
         data.extend(vec![2u8; 50]);
-        let compressed = compressor
-            .compress(&data, CompressionLevel::default())
-            .unwrap();
+        let compressed = compressor.compress(&data).unwrap();
         let decompressed = compressor.decompress(&compressed, None).unwrap();
         assert_eq!(decompressed, data);
     }
@@ -67,6 +67,12 @@ pub enum VdsError {
 
     #[error("Configuration error: {0}")]
     Configuration(String),
+
+    #[error("Processing error: {0}")]
+    Processing(String),
+
+    #[error("No compressor registered for codec id {0}")]
+    UnregisteredCodec(u8),
 }
 
 /// Specialized Result type for VDS operations
@@ -0,0 +1,551 @@
+//! Histograms and percentiles for display clipping
+//!
+//! Histograms cover a whole volume, a LOD level or a voxel region. Exact histograms scan every
+//! brick; sampled histograms read an evenly spaced subset of bricks and report percentile error
+//! bounds. Results are cached in the volume metadata.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::layout::VolumeDataLayout;
+use crate::types::ValueRange;
+use crate::utils::{brick_path, bytes_to_f64, copy_box, BufferRegion};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+/// Default number of histogram bins
+pub const DEFAULT_BINS: usize = 256;
+
+/// Number of bricks decoded concurrently
+const MAX_CONCURRENT_BRICKS: usize = 16;
+
+/// Confidence level of the percentile error bounds of sampled histograms
+const CONFIDENCE: f64 = 0.95;
+
+/// What to histogram and how
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramOptions {
+    /// Number of bins
+    pub bins: usize,
+    /// Level of detail to read
+    pub lod: usize,
+    /// Voxel box `[min, max)` in the LOD's voxel grid, or the whole LOD
+    pub region: Option<(Vec<usize>, Vec<usize>)>,
+    /// Histogram range; defaults to the volume statistics, or a min/max pass if there are none
+    pub range: Option<ValueRange>,
+    /// Read only this many evenly spaced bricks instead of all of them
+    pub sampled_bricks: Option<usize>,
+}
+
+impl Default for HistogramOptions {
+    fn default() -> Self {
+        Self {
+            bins: DEFAULT_BINS,
+            lod: 0,
+            region: None,
+            range: None,
+            sampled_bricks: None,
+        }
+    }
+}
+
+impl HistogramOptions {
+    /// Exact histogram of the whole volume with `bins` bins
+    pub fn new(bins: usize) -> Self {
+        Self {
+            bins,
+            ..Self::default()
+        }
+    }
+
+    /// Set the level of detail
+    pub fn with_lod(mut self, lod: usize) -> Self {
+        self.lod = lod;
+        self
+    }
+
+    /// Restrict to the voxel box `[min_coords, max_coords)`
+    pub fn with_region(mut self, min_coords: Vec<usize>, max_coords: Vec<usize>) -> Self {
+        self.region = Some((min_coords, max_coords));
+        self
+    }
+
+    /// Set the histogram range
+    pub fn with_range(mut self, range: ValueRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Approximate from `bricks` evenly spaced bricks
+    pub fn with_sampled_bricks(mut self, bricks: usize) -> Self {
+        self.sampled_bricks = Some(bricks);
+        self
+    }
+
+    /// Key identifying these options in the metadata cache
+    fn cache_key(&self) -> String {
+        let mut key = format!("lod{}/bins{}", self.lod, self.bins);
+        if let Some((min, max)) = &self.region {
+            key.push_str(&format!("/region{:?}-{:?}", min, max));
+        }
+        if let Some(range) = &self.range {
+            key.push_str(&format!("/range{}:{}", range.min, range.max));
+        }
+        if let Some(bricks) = self.sampled_bricks {
+            key.push_str(&format!("/sampled{}", bricks));
+        }
+        key
+    }
+}
+
+/// Histogram of sample values over a fixed range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// Range covered by the bins; the last bin includes `range.max`
+    pub range: ValueRange,
+    /// Sample count per equal-width bin
+    pub bins: Vec<u64>,
+    /// Finite samples below the range
+    pub below: u64,
+    /// Finite samples above the range
+    pub above: u64,
+    /// NaN and infinite samples, excluded from percentiles
+    pub non_finite: u64,
+    /// Whether every brick was read
+    pub exact: bool,
+}
+
+/// A percentile with bounds on the true value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PercentileEstimate {
+    /// Interpolated percentile value
+    pub value: f64,
+    /// Lower bound on the true percentile
+    pub lower: f64,
+    /// Upper bound on the true percentile
+    pub upper: f64,
+    /// Bound on the rank error as a fraction of the samples (zero for exact histograms)
+    pub rank_error: f64,
+}
+
+impl Histogram {
+    /// Empty exact histogram
+    pub fn new(range: ValueRange, bins: usize) -> Self {
+        Self {
+            range,
+            bins: vec![0; bins.max(1)],
+            below: 0,
+            above: 0,
+            non_finite: 0,
+            exact: true,
+        }
+    }
+
+    /// Width of each bin
+    pub fn bin_width(&self) -> f64 {
+        (self.range.max - self.range.min) / self.bins.len() as f64
+    }
+
+    /// Add a sample
+    pub fn add(&mut self, value: f64) {
+        if !value.is_finite() {
+            self.non_finite += 1;
+        } else if value < self.range.min {
+            self.below += 1;
+        } else if value > self.range.max {
+            self.above += 1;
+        } else {
+            let width = self.bin_width();
+            let bin = if width > 0.0 {
+                ((value - self.range.min) / width) as usize
+            } else {
+                0
+            };
+            let last = self.bins.len() - 1;
+            self.bins[bin.min(last)] += 1;
+        }
+    }
+
+    /// Add the counts of a histogram with the same range and bins
+    pub fn merge(&mut self, other: &Histogram) {
+        debug_assert_eq!(self.bins.len(), other.bins.len());
+        for (bin, count) in self.bins.iter_mut().zip(&other.bins) {
+            *bin += count;
+        }
+        self.below += other.below;
+        self.above += other.above;
+        self.non_finite += other.non_finite;
+        self.exact &= other.exact;
+    }
+
+    /// Number of finite samples
+    pub fn count(&self) -> u64 {
+        self.bins.iter().sum::<u64>() + self.below + self.above
+    }
+
+    /// Value below which `percentile` percent of the finite samples fall
+    ///
+    /// Interpolates linearly within a bin. Percentiles falling below or above the range are
+    /// clamped to it. Returns NaN for an empty histogram.
+    pub fn percentile(&self, percentile: f64) -> f64 {
+        let count = self.count();
+        if count == 0 {
+            return f64::NAN;
+        }
+
+        let rank = percentile.clamp(0.0, 100.0) / 100.0 * count as f64;
+        let mut cumulative = self.below as f64;
+        if rank <= cumulative {
+            return self.range.min;
+        }
+        let width = self.bin_width();
+        for (i, &bin) in self.bins.iter().enumerate() {
+            let bin = bin as f64;
+            if bin > 0.0 && rank <= cumulative + bin {
+                return self.range.min + (i as f64 + (rank - cumulative) / bin) * width;
+            }
+            cumulative += bin;
+        }
+        self.range.max
+    }
+
+    /// Percentile with bounds that hold exactly for exact histograms and with 95% confidence
+    /// for sampled ones
+    ///
+    /// The value is only known to bin resolution, so the bounds span whole bins. Sampled
+    /// histograms widen them by the Dvoretzky–Kiefer–Wolfowitz rank error for the number of
+    /// samples read, which assumes the sampled bricks are representative of the volume.
+    pub fn percentile_estimate(&self, percentile: f64) -> PercentileEstimate {
+        let count = self.count();
+        let rank_error = if self.exact || count == 0 {
+            0.0
+        } else {
+            ((2.0 / (1.0 - CONFIDENCE)).ln() / (2.0 * count as f64)).sqrt()
+        };
+
+        let width = self.bin_width();
+        let bin_edge = |value: f64, upper: bool| {
+            if width <= 0.0 || !value.is_finite() {
+                return value;
+            }
+            let bin = ((value - self.range.min) / width).floor() + if upper { 1.0 } else { 0.0 };
+            (self.range.min + bin * width).clamp(self.range.min, self.range.max)
+        };
+
+        PercentileEstimate {
+            value: self.percentile(percentile),
+            lower: bin_edge(self.percentile(percentile - 100.0 * rank_error), false),
+            upper: bin_edge(self.percentile(percentile + 100.0 * rank_error), true),
+            rank_error,
+        }
+    }
+
+    /// Display clip range between two percentiles, e.g. 1 and 99
+    pub fn clip_range(&self, low: f64, high: f64) -> ValueRange {
+        ValueRange::new(self.percentile(low), self.percentile(high))
+    }
+}
+
+impl VolumeDataAccess {
+    /// Histogram for `options`, from the metadata cache if available
+    pub async fn histogram(&self, options: &HistogramOptions) -> Result<Histogram> {
+        if let Some(cached) = self.metadata().histograms.get(&options.cache_key()) {
+            return Ok(cached.clone());
+        }
+        self.compute_histogram(options).await
+    }
+
+    /// Compute a histogram and cache it in the metadata, replacing any earlier result
+    ///
+    /// Cached histograms are dropped whenever a brick is written.
+    pub async fn compute_histogram(&self, options: &HistogramOptions) -> Result<Histogram> {
+        let layout = self.layout().lod_layout(options.lod)?;
+        let (region_min, region_max) = match &options.region {
+            Some((min, max)) => (min.clone(), max.clone()),
+            None => (vec![0; layout.dimensionality], layout.size()),
+        };
+        let size = layout.size();
+        if region_min.len() != layout.dimensionality
+            || region_max.len() != layout.dimensionality
+            || (0..layout.dimensionality)
+                .any(|d| region_min[d] >= region_max[d] || region_max[d] > size[d])
+        {
+            return Err(VdsError::OutOfBounds(format!(
+                "Invalid histogram region {:?} - {:?} for volume size {:?}",
+                region_min, region_max, size
+            )));
+        }
+
+        // Bricks intersecting the region, thinned to an even sample if requested
+        let mut bricks: Vec<usize> = (0..layout.total_bricks())
+            .filter(|&index| {
+                let range = layout.brick_data_range(&layout.brick_index_to_coords(index));
+                range
+                    .iter()
+                    .enumerate()
+                    .all(|(d, &(start, end))| start < region_max[d] && end > region_min[d])
+            })
+            .collect();
+        let exact = match options.sampled_bricks {
+            Some(sampled) if sampled < bricks.len() => {
+                let total = bricks.len();
+                bricks = (0..sampled.max(1))
+                    .map(|k| bricks[k * total / sampled.max(1)])
+                    .collect();
+                false
+            }
+            _ => true,
+        };
+
+        let region = (region_min.as_slice(), region_max.as_slice());
+        let range = match options.range {
+            Some(range) => range,
+            None => match self.metadata().statistics {
+                Some(stats) if stats.count > 0 => ValueRange::new(stats.min, stats.max),
+                _ => {
+                    let mut range = ValueRange::new(f64::INFINITY, f64::NEG_INFINITY);
+                    self.scan_bricks(&layout, options.lod, &bricks, region, |values| {
+                        for &value in values.iter().filter(|v| v.is_finite()) {
+                            range.min = range.min.min(value);
+                            range.max = range.max.max(value);
+                        }
+                    })
+                    .await?;
+                    if range.is_valid() {
+                        range
+                    } else {
+                        ValueRange::new(0.0, 0.0)
+                    }
+                }
+            },
+        };
+
+        let mut histogram = Histogram::new(range, options.bins);
+        self.scan_bricks(&layout, options.lod, &bricks, region, |values| {
+            values.iter().for_each(|&value| histogram.add(value))
+        })
+        .await?;
+        histogram.exact = exact;
+
+        let key = options.cache_key();
+        let cached = histogram.clone();
+        self.update_metadata(|metadata| {
+            metadata.histograms.insert(key, cached);
+        })
+        .await?;
+
+        Ok(histogram)
+    }
+
+    /// Decode the part of each stored brick inside `region` and pass its samples to `visit`
+    ///
+    /// LOD 0 bricks that have never been written are skipped. Coarser LOD bricks only exist
+    /// once generated, so a missing one is an error rather than an empty brick.
+    async fn scan_bricks(
+        &self,
+        layout: &VolumeDataLayout,
+        lod: usize,
+        bricks: &[usize],
+        (region_min, region_max): (&[usize], &[usize]),
+        mut visit: impl FnMut(&[f64]),
+    ) -> Result<()> {
+        let element_size = layout.data_type.size_in_bytes();
+        let mut reads = stream::iter(bricks.iter().copied())
+            .map(|index| async move {
+                if !self.io_manager().exists(&brick_path(index, lod)).await? {
+                    if lod > 0 {
+                        return Err(VdsError::NotFound(format!(
+                            "LOD {} brick {} has not been generated",
+                            lod, index
+                        )));
+                    }
+                    return Ok::<_, VdsError>(None);
+                }
+                let data = self.read_lod_brick(lod, index).await?;
+
+                let range = layout.brick_data_range(&layout.brick_index_to_coords(index));
+                let box_min: Vec<usize> = range
+                    .iter()
+                    .zip(region_min)
+                    .map(|(&(start, _), &min)| start.max(min))
+                    .collect();
+                let box_max: Vec<usize> = range
+                    .iter()
+                    .zip(region_max)
+                    .map(|(&(_, end), &max)| end.min(max))
+                    .collect();
+                if range
+                    .iter()
+                    .zip(box_min.iter().zip(&box_max))
+                    .all(|(&(start, end), (&min, &max))| start == min && end == max)
+                {
+                    return bytes_to_f64(&data, layout.data_type).map(Some);
+                }
+
+                let origin: Vec<usize> = range.iter().map(|&(start, _)| start).collect();
+                let dims: Vec<usize> = range.iter().map(|&(start, end)| end - start).collect();
+                let box_dims: Vec<usize> = box_min
+                    .iter()
+                    .zip(&box_max)
+                    .map(|(min, max)| max - min)
+                    .collect();
+                let mut clipped = vec![0u8; box_dims.iter().product::<usize>() * element_size];
+                copy_box(
+                    &data,
+                    BufferRegion {
+                        origin: &origin,
+                        dims: &dims,
+                    },
+                    &mut clipped,
+                    BufferRegion {
+                        origin: &box_min,
+                        dims: &box_dims,
+                    },
+                    &box_min,
+                    &box_max,
+                    element_size,
+                );
+                bytes_to_f64(&clipped, layout.data_type).map(Some)
+            })
+            .buffer_unordered(MAX_CONCURRENT_BRICKS);
+
+        while let Some(values) = reads.try_next().await? {
+            if let Some(values) = values {
+                visit(&values);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::BrickSize;
+    use crate::metadata::VdsMetadata;
+    use crate::types::{AxisDescriptor, DataType};
+    use crate::utils::typed_data_to_bytes;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = Histogram::new(ValueRange::new(0.0, 100.0), 100);
+        (0..1000).for_each(|v| histogram.add(v as f64 / 10.0));
+        histogram.add(f64::NAN);
+        histogram.add(-5.0);
+
+        assert_eq!(histogram.count(), 1001);
+        assert_eq!(histogram.non_finite, 1);
+        assert!((histogram.percentile(50.0) - 50.0).abs() < 0.2);
+        assert_eq!(histogram.percentile(0.0), 0.0);
+        assert_eq!(histogram.percentile(100.0), 100.0);
+
+        let estimate = histogram.percentile_estimate(99.0);
+        assert_eq!(estimate.rank_error, 0.0);
+        assert!(estimate.lower <= estimate.value && estimate.value <= estimate.upper);
+        assert!(estimate.upper - estimate.lower <= histogram.bin_width());
+
+        histogram.exact = false;
+        let estimate = histogram.percentile_estimate(99.0);
+        assert!(estimate.rank_error > 0.0);
+        assert!(estimate.upper - estimate.lower > histogram.bin_width());
+
+        let clip = histogram.clip_range(1.0, 99.0);
+        assert!(clip.min < 2.0 && clip.max > 98.0);
+    }
+
+    #[tokio::test]
+    async fn test_volume_histogram() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(8, "Inline", "unitless", 0.0, 7.0),
+            AxisDescriptor::new(10, "Sample", "ms", 0.0, 36.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 4, 1, 1, 1, 1]))
+            .with_lod_levels(2);
+        let vds = VolumeDataAccess::create(path, VdsMetadata::new(layout))
+            .await
+            .unwrap();
+        let values: Vec<f32> = (0..80).map(|v| v as f32).collect();
+        vds.write_slice(&[0, 0], &[8, 10], &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+
+        // Range found by a min/max pass
+        let exact = vds.histogram(&HistogramOptions::new(80)).await.unwrap();
+        assert!(exact.exact);
+        assert_eq!(exact.range, ValueRange::new(0.0, 79.0));
+        assert_eq!(exact.count(), 80);
+        assert!((exact.percentile(50.0) - 39.5).abs() < 1.0);
+
+        // Region spanning four bricks, partially
+        let options = HistogramOptions::new(10)
+            .with_region(vec![2, 3], vec![6, 5])
+            .with_range(ValueRange::new(0.0, 100.0));
+        let region = vds.compute_histogram(&options).await.unwrap();
+        assert_eq!(region.count(), 8);
+        assert_eq!(region.bins[2], 2);
+        assert_eq!(region.bins[5], 2);
+
+        let sampled = vds
+            .compute_histogram(&HistogramOptions::new(16).with_sampled_bricks(3))
+            .await
+            .unwrap();
+        assert!(!sampled.exact);
+        assert!(sampled.count() < 80);
+
+        // Cached results survive reopening
+        let reopened = VolumeDataAccess::open(path).await.unwrap();
+        assert_eq!(reopened.metadata().histograms.len(), 3);
+        assert_eq!(reopened.histogram(&options).await.unwrap(), region);
+
+        // No LOD bricks have been generated
+        assert!(matches!(
+            reopened
+                .compute_histogram(&HistogramOptions::new(16).with_lod(1))
+                .await,
+            Err(VdsError::NotFound(_))
+        ));
+        assert_eq!(reopened.metadata().histograms.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_write_invalidates_histograms() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(4, "Inline", "unitless", 0.0, 3.0),
+            AxisDescriptor::new(4, "Sample", "ms", 0.0, 12.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes).unwrap();
+        let vds = VolumeDataAccess::create(path, VdsMetadata::new(layout))
+            .await
+            .unwrap();
+        let options = HistogramOptions::new(4).with_range(ValueRange::new(0.0, 4.0));
+
+        vds.write_slice(&[0, 0], &[4, 4], &typed_data_to_bytes(&[0.5f32; 16]))
+            .await
+            .unwrap();
+        assert_eq!(
+            vds.histogram(&options).await.unwrap().bins,
+            vec![16, 0, 0, 0]
+        );
+
+        vds.write_slice(&[0, 0], &[2, 4], &typed_data_to_bytes(&[3.5f32; 8]))
+            .await
+            .unwrap();
+        assert_eq!(
+            vds.histogram(&options).await.unwrap().bins,
+            vec![8, 0, 0, 8]
+        );
+        assert!(VolumeDataAccess::open(path)
+            .await
+            .unwrap()
+            .metadata()
+            .histograms
+            .values()
+            .all(|histogram| histogram.bins == vec![8, 0, 0, 8]));
+    }
+}
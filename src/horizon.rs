@@ -0,0 +1,347 @@
+//! Horizon extraction: sampling a volume along an interpreted surface
+//!
+//! A horizon is a grid of times/depths over the full inline x crossline extent of the volume.
+//! Each defined node is sampled along the sample axis, optionally aggregated over a window.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::interpolation::{interpolate, SampleInterpolation};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use ndarray::Array2;
+use std::collections::BTreeMap;
+
+/// Number of brick columns read concurrently
+const MAX_CONCURRENT_COLUMNS: usize = 8;
+
+/// Aggregate computed over a window of samples around the horizon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowAggregate {
+    /// Root mean square amplitude
+    Rms,
+    /// Maximum absolute amplitude
+    MaxAbs,
+    /// Mean amplitude
+    Mean,
+}
+
+/// Options for [`VolumeDataAccess::extract_horizon`]
+#[derive(Debug, Clone, Copy)]
+pub struct HorizonOptions {
+    /// Interpolation along the sample axis
+    pub interpolation: SampleInterpolation,
+    /// Optional aggregate over `±half_width` samples around each node
+    pub window: Option<(WindowAggregate, usize)>,
+}
+
+impl Default for HorizonOptions {
+    fn default() -> Self {
+        Self {
+            interpolation: SampleInterpolation::Linear,
+            window: None,
+        }
+    }
+}
+
+impl HorizonOptions {
+    /// Set the sample interpolation
+    pub fn with_interpolation(mut self, interpolation: SampleInterpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Aggregate over `±half_width` samples instead of returning the single amplitude
+    pub fn with_window(mut self, aggregate: WindowAggregate, half_width: usize) -> Self {
+        self.window = Some((aggregate, half_width));
+        self
+    }
+}
+
+impl VolumeDataAccess {
+    /// Sample the volume along a horizon
+    ///
+    /// `horizon` is indexed `[inline, crossline]` over the full volume grid and holds sample
+    /// axis annotation values (e.g. ms). NaN nodes, and nodes outside the sample axis, yield NaN.
+    ///
+    /// Bricks are read brick column by brick column, covering only the sample range the surface
+    /// (plus interpolation and window margins) spans in that column, so each brick is read at
+    /// most once.
+    pub async fn extract_horizon(
+        &self,
+        horizon: &Array2<f64>,
+        options: HorizonOptions,
+    ) -> Result<Array2<f32>> {
+        let layout = self.layout();
+        let axes = layout.seismic_axes()?;
+        let inlines = layout.axes[axes.inline].num_samples;
+        let crosslines = layout.axes[axes.crossline].num_samples;
+        let sample_axis = layout.axes[axes.sample].clone();
+        let num_samples = sample_axis.num_samples;
+
+        if horizon.dim() != (inlines, crosslines) {
+            return Err(VdsError::InvalidDimensions(format!(
+                "Horizon grid is {:?}, volume has {} inlines x {} crosslines",
+                horizon.dim(),
+                inlines,
+                crosslines
+            )));
+        }
+        for (dim, axis) in layout.axes.iter().enumerate() {
+            if dim != axes.inline
+                && dim != axes.crossline
+                && dim != axes.sample
+                && axis.num_samples != 1
+            {
+                return Err(VdsError::InvalidDimensions(format!(
+                    "Cannot extract a horizon from a volume with {} samples on axis {}",
+                    axis.num_samples, axis.name
+                )));
+            }
+        }
+
+        let half_window = options.window.map_or(0, |(_, half)| half);
+        // Interpolating at fractional index `p` reads samples `floor(p) - h + 1 ..= floor(p) + h`;
+        // nearest may round up, so it reads `floor(p) ..= floor(p) + 1`
+        let half_width = options.interpolation.half_width().max(1);
+        let below = (half_width - 1 + half_window) as isize;
+        let above = (half_width + half_window) as isize;
+
+        // Fractional sample position of every node, NaN if undefined
+        let positions = horizon.mapv(|t| {
+            let position = sample_axis.coord_to_fractional_index(t);
+            if (-0.5..=num_samples as f64 - 0.5).contains(&position) {
+                position
+            } else {
+                f64::NAN
+            }
+        });
+
+        // Lateral and vertical extent of the surface in each brick column
+        let inline_brick = layout.brick_size.get(axes.inline);
+        let crossline_brick = layout.brick_size.get(axes.crossline);
+        let mut columns: BTreeMap<(usize, usize), [usize; 6]> = BTreeMap::new();
+        for ((il, xl), &position) in positions.indexed_iter() {
+            if position.is_nan() {
+                continue;
+            }
+            let last = num_samples as isize - 1;
+            let low = (position.floor() as isize - below).clamp(0, last) as usize;
+            let high = (position.floor() as isize + above).clamp(0, last) as usize + 1;
+            columns
+                .entry((il / inline_brick, xl / crossline_brick))
+                .and_modify(|b| {
+                    b[0] = b[0].min(il);
+                    b[1] = b[1].max(il + 1);
+                    b[2] = b[2].min(xl);
+                    b[3] = b[3].max(xl + 1);
+                    b[4] = b[4].min(low);
+                    b[5] = b[5].max(high);
+                })
+                .or_insert([il, il + 1, xl, xl + 1, low, high]);
+        }
+
+        // Read columns in brick order with bounded concurrency
+        let reads = stream::iter(columns.into_values().map(|bounds| {
+            let mut min_coords = vec![0; layout.dimensionality];
+            let mut max_coords = vec![1; layout.dimensionality];
+            min_coords[axes.inline] = bounds[0];
+            max_coords[axes.inline] = bounds[1];
+            min_coords[axes.crossline] = bounds[2];
+            max_coords[axes.crossline] = bounds[3];
+            min_coords[axes.sample] = bounds[4];
+            max_coords[axes.sample] = bounds[5];
+            async move {
+                let samples = self.read_slice_f32(&min_coords, &max_coords).await?;
+                Ok::<_, VdsError>((min_coords, max_coords, samples))
+            }
+        }))
+        .buffered(MAX_CONCURRENT_COLUMNS);
+
+        let mut result = Array2::from_elem((inlines, crosslines), f32::NAN);
+        let result = reads
+            .try_fold(&mut result, |result, (min_coords, max_coords, samples)| {
+                let dims: Vec<usize> = min_coords
+                    .iter()
+                    .zip(max_coords.iter())
+                    .map(|(min, max)| max - min)
+                    .collect();
+                let mut strides = vec![1usize; dims.len()];
+                for dim in (0..dims.len().saturating_sub(1)).rev() {
+                    strides[dim] = strides[dim + 1] * dims[dim + 1];
+                }
+
+                let sample_offset = min_coords[axes.sample] as f64;
+                let mut trace = vec![0f32; dims[axes.sample]];
+                for il in min_coords[axes.inline]..max_coords[axes.inline] {
+                    for xl in min_coords[axes.crossline]..max_coords[axes.crossline] {
+                        let position = positions[[il, xl]];
+                        if position.is_nan() {
+                            continue;
+                        }
+                        let base = (il - min_coords[axes.inline]) * strides[axes.inline]
+                            + (xl - min_coords[axes.crossline]) * strides[axes.crossline];
+                        for (k, value) in trace.iter_mut().enumerate() {
+                            *value = samples[base + k * strides[axes.sample]];
+                        }
+
+                        // Positions are relative to the partial trace; clamping at its ends
+                        // matches clamping at the volume ends since every tap is included
+                        let local = position - sample_offset;
+                        result[[il, xl]] = match options.window {
+                            None => interpolate(&trace, local, options.interpolation),
+                            Some((aggregate, half)) => window_aggregate(
+                                &trace,
+                                local,
+                                half,
+                                aggregate,
+                                options.interpolation,
+                            ),
+                        };
+                    }
+                }
+                futures::future::ready(Ok(result))
+            })
+            .await?;
+
+        Ok(result.to_owned())
+    }
+}
+
+/// Aggregate interpolated samples at `position ± k` for `k` in `0..=half_width`
+fn window_aggregate(
+    trace: &[f32],
+    position: f64,
+    half_width: usize,
+    aggregate: WindowAggregate,
+    interpolation: SampleInterpolation,
+) -> f32 {
+    let half = half_width as isize;
+    let values =
+        (-half..=half).map(|k| interpolate(trace, position + k as f64, interpolation) as f64);
+    let count = (2 * half_width + 1) as f64;
+
+    let value = match aggregate {
+        WindowAggregate::Rms => (values.map(|v| v * v).sum::<f64>() / count).sqrt(),
+        WindowAggregate::MaxAbs => values.map(f64::abs).fold(0.0, f64::max),
+        WindowAggregate::Mean => values.sum::<f64>() / count,
+    };
+    value as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::metadata::VdsMetadata;
+    use crate::types::{AxisDescriptor, DataType};
+    use crate::utils::typed_data_to_bytes;
+    use tempfile::TempDir;
+
+    const INLINES: usize = 4;
+    const CROSSLINES: usize = 3;
+    const SAMPLES: usize = 16;
+
+    /// Value is linear along the sample axis: il * 1000 + xl * 100 + s
+    fn value(il: usize, xl: usize, s: f64) -> f64 {
+        (il * 1000 + xl * 100) as f64 + s
+    }
+
+    /// Volume with only the first sample brick (samples 0-3) written
+    async fn create_test_volume(dir: &TempDir) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(INLINES, "Inline", "unitless", 1.0, 4.0),
+            AxisDescriptor::new(CROSSLINES, "Crossline", "unitless", 1.0, 3.0),
+            AxisDescriptor::new(SAMPLES, "Time", "ms", 0.0, 60.0),
+        ];
+        let layout = VolumeDataLayout::new(3, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([2, 2, 4, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(dir.path().to_str().unwrap(), VdsMetadata::new(layout))
+            .await
+            .unwrap();
+
+        let mut values = Vec::new();
+        for il in 0..INLINES {
+            for xl in 0..CROSSLINES {
+                for s in 0..4 {
+                    values.push(value(il, xl, s as f64) as f32);
+                }
+            }
+        }
+        vds.write_slice(
+            &[0, 0, 0],
+            &[INLINES, CROSSLINES, 4],
+            &typed_data_to_bytes(&values),
+        )
+        .await
+        .unwrap();
+        vds
+    }
+
+    #[tokio::test]
+    async fn test_extract_horizon_interpolation() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+
+        // 6 ms is sample 1.5; a few undefined or out-of-range nodes
+        let mut horizon = Array2::from_elem((INLINES, CROSSLINES), 6.0);
+        horizon[[0, 1]] = f64::NAN;
+        horizon[[3, 2]] = 500.0;
+
+        let linear = vds
+            .extract_horizon(&horizon, HorizonOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(linear[[2, 1]] as f64, value(2, 1, 1.5));
+        assert!(linear[[0, 1]].is_nan());
+        assert!(linear[[3, 2]].is_nan());
+
+        let nearest = vds
+            .extract_horizon(
+                &horizon,
+                HorizonOptions::default().with_interpolation(SampleInterpolation::Nearest),
+            )
+            .await
+            .unwrap();
+        assert_eq!(nearest[[1, 0]] as f64, value(1, 0, 2.0));
+
+        let cubic = vds
+            .extract_horizon(
+                &horizon,
+                HorizonOptions::default().with_interpolation(SampleInterpolation::Cubic),
+            )
+            .await
+            .unwrap();
+        assert!((cubic[[3, 1]] as f64 - value(3, 1, 1.5)).abs() < 1e-3);
+    }
+
+    #[tokio::test]
+    async fn test_extract_horizon_windowed() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+
+        // 4 ms is sample 1; window covers samples 0..=2
+        let horizon = Array2::from_elem((INLINES, CROSSLINES), 4.0);
+        let extract = |aggregate| {
+            vds.extract_horizon(
+                &horizon,
+                HorizonOptions::default().with_window(aggregate, 1),
+            )
+        };
+
+        let mean = extract(WindowAggregate::Mean).await.unwrap();
+        assert_eq!(mean[[1, 1]] as f64, value(1, 1, 1.0));
+
+        let max_abs = extract(WindowAggregate::MaxAbs).await.unwrap();
+        assert_eq!(max_abs[[1, 1]] as f64, value(1, 1, 2.0));
+
+        let rms = extract(WindowAggregate::Rms).await.unwrap();
+        let expected = ((0..3).map(|s| value(0, 0, s as f64).powi(2)).sum::<f64>() / 3.0).sqrt();
+        assert!((rms[[0, 0]] as f64 - expected).abs() < 1e-4);
+
+        let wrong_shape = Array2::from_elem((2, 2), 4.0);
+        assert!(vds
+            .extract_horizon(&wrong_shape, HorizonOptions::default())
+            .await
+            .is_err());
+    }
+}
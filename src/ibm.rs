@@ -0,0 +1,168 @@
+//! IBM System/360 floating point conversion
+//!
+//! IBM single precision floats have a sign bit, a 7-bit base-16 exponent biased by 64 and a
+//! 24-bit fraction. They cover roughly 5.4e-79 to 7.2e75, a superset of the `f32` range, but
+//! have no infinities or NaN. Words are stored big-endian, as in SEG-Y.
+
+/// Largest IBM magnitude, written for infinities
+pub const IBM_MAX: u32 = 0x7FFF_FFFF;
+
+/// Convert an IBM float bit pattern to `f32`
+///
+/// Unnormalised fractions are accepted. Magnitudes beyond the `f32` range become infinities and
+/// tiny magnitudes round to `f32` denormals or zero.
+pub fn ibm_to_ieee(bits: u32) -> f32 {
+    let negative = bits & 0x8000_0000 != 0;
+    let exponent = ((bits >> 24) & 0x7F) as i32;
+    let fraction = bits & 0x00FF_FFFF;
+
+    // Exact in f64 (24-bit fraction, binary exponent within -280..=228), so the final
+    // narrowing is the only rounding step
+    let magnitude = fraction as f64 * 2f64.powi(4 * (exponent - 64) - 24);
+    let value = magnitude as f32;
+    if negative {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Convert an `f32` to an IBM float bit pattern, rounding to nearest-even
+///
+/// Denormals are normalised, infinities saturate to [`IBM_MAX`] with their sign and NaN becomes
+/// zero, the conventional SEG-Y "no value".
+pub fn ieee_to_ibm(value: f32) -> u32 {
+    let bits = value.to_bits();
+    let sign = bits & 0x8000_0000;
+
+    if value.is_nan() {
+        return 0;
+    }
+    if value.is_infinite() {
+        return sign | IBM_MAX;
+    }
+
+    let biased = ((bits >> 23) & 0xFF) as i32;
+    let (mantissa, exponent) = if biased == 0 {
+        (bits & 0x007F_FFFF, -149)
+    } else {
+        ((bits & 0x007F_FFFF) | 0x0080_0000, biased - 150)
+    };
+    if mantissa == 0 {
+        return sign;
+    }
+
+    // value = mantissa * 2^exponent < 2^top; pick the smallest hex exponent covering it
+    let top = 32 - mantissa.leading_zeros() as i32 + exponent;
+    let hex_exponent = top.div_euclid(4) + i32::from(top.rem_euclid(4) != 0);
+    let shift = exponent + 24 - 4 * hex_exponent;
+
+    // A right shift drops at most 3 bits of a 24-bit mantissa, so rounding can't carry out
+    let fraction = if shift >= 0 {
+        mantissa << shift
+    } else {
+        round_shift_right(mantissa, (-shift) as u32)
+    };
+
+    // The f32 range always fits the IBM exponent range, so no clamping is needed
+    sign | (((hex_exponent + 64) as u32) << 24) | fraction
+}
+
+/// Decode big-endian IBM words into `f32` samples
+///
+/// Trailing bytes that don't form a whole word are ignored.
+pub fn decode_be(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|word| ibm_to_ieee(u32::from_be_bytes(word.try_into().unwrap())))
+        .collect()
+}
+
+/// Encode `f32` samples as big-endian IBM words
+pub fn encode_be(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|&v| ieee_to_ibm(v).to_be_bytes())
+        .collect()
+}
+
+/// Shift right with round-half-to-even
+fn round_shift_right(value: u32, shift: u32) -> u32 {
+    if shift >= 32 {
+        return 0;
+    }
+    let truncated = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    if remainder > half || (remainder == half && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(ieee_to_ibm(1.0), 0x4110_0000);
+        assert_eq!(ieee_to_ibm(-118.625), 0xC276_A000);
+        assert_eq!(ieee_to_ibm(0.15625), 0x4028_0000);
+        assert_eq!(ibm_to_ieee(0x4110_0000), 1.0);
+        assert_eq!(ibm_to_ieee(0xC276_A000), -118.625);
+        assert_eq!(ibm_to_ieee(0x4028_0000), 0.15625);
+    }
+
+    #[test]
+    fn test_special_values() {
+        assert_eq!(ieee_to_ibm(0.0), 0);
+        assert_eq!(ieee_to_ibm(-0.0), 0x8000_0000);
+        assert_eq!(ieee_to_ibm(f32::NAN), 0);
+        assert_eq!(ieee_to_ibm(f32::INFINITY), IBM_MAX);
+        assert_eq!(ieee_to_ibm(f32::NEG_INFINITY), 0x8000_0000 | IBM_MAX);
+
+        // IBM magnitudes beyond f32 overflow to infinity, tiny ones underflow
+        assert_eq!(ibm_to_ieee(IBM_MAX), f32::INFINITY);
+        assert_eq!(ibm_to_ieee(0x8000_0000 | IBM_MAX), f32::NEG_INFINITY);
+        assert_eq!(ibm_to_ieee(0x0010_0000), 0.0);
+
+        // Unnormalised fraction
+        assert_eq!(ibm_to_ieee(0x4100_0001), 2f32.powi(-20));
+    }
+
+    #[test]
+    fn test_denormals_round_trip() {
+        // Short denormal mantissas survive hex normalisation exactly
+        for value in [
+            f32::from_bits(1),
+            f32::from_bits(0x0004_5678),
+            -f32::MIN_POSITIVE,
+        ] {
+            let ibm = ieee_to_ibm(value);
+            assert_eq!(ibm_to_ieee(ibm), value);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_precision() {
+        // Hex normalisation costs at most 3 bits of precision
+        for value in [1.0f32, 7.389, -2.5e-20, 1.0e30, f32::MAX, 12345.678] {
+            let back = ibm_to_ieee(ieee_to_ibm(value));
+            assert!((back - value).abs() <= value.abs() * 2f32.powi(-21));
+        }
+        assert_eq!(ibm_to_ieee(ieee_to_ibm(f32::MAX)), f32::MAX);
+
+        // 2 - ulp needs 25 significant bits in hex form and rounds up to 2.0
+        assert_eq!(ieee_to_ibm(f32::from_bits(0x3FFF_FFFF)), 0x4120_0000);
+    }
+
+    #[test]
+    fn test_slice_conversion() {
+        let values = [1.0f32, -118.625, 0.0];
+        let bytes = encode_be(&values);
+        assert_eq!(&bytes[..4], &[0x41, 0x10, 0x00, 0x00]);
+        assert_eq!(decode_be(&bytes), values);
+    }
+}
@@ -0,0 +1,367 @@
+//! Rendering sections to RGBA images and PNG
+//!
+//! A [`Section`] is drawn with its rows along the image x axis and its columns along y, so
+//! inline and crossline sections show traces side by side with time/depth increasing downwards.
+//! Values are clipped, optionally gain-controlled, and mapped through a seismic colormap;
+//! NaN and infinite samples are transparent.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::section::Section;
+use ndarray::{Array2, Axis};
+
+/// Colour lookup for normalised values in `[0, 1]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    /// Black to white
+    Grey,
+    /// Red through white to blue
+    RedWhiteBlue,
+    /// Dark blue, blue, white, red, dark red
+    Seismic,
+}
+
+impl Colormap {
+    /// Control points, evenly spaced over `[0, 1]`
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Grey => &[[0, 0, 0], [255, 255, 255]],
+            Colormap::RedWhiteBlue => &[[255, 0, 0], [255, 255, 255], [0, 0, 255]],
+            Colormap::Seismic => &[
+                [0, 0, 77],
+                [0, 0, 255],
+                [255, 255, 255],
+                [255, 0, 0],
+                [128, 0, 0],
+            ],
+        }
+    }
+
+    /// Opaque RGBA colour of `t`, clamped to `[0, 1]`
+    pub fn color(&self, t: f64) -> [u8; 4] {
+        let stops = self.stops();
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let i = (position.floor() as usize).min(stops.len() - 2);
+        let frac = position - i as f64;
+        let channel =
+            |c: usize| stops[i][c] as f64 + frac * (stops[i + 1][c] as f64 - stops[i][c] as f64);
+        [
+            channel(0).round() as u8,
+            channel(1).round() as u8,
+            channel(2).round() as u8,
+            255,
+        ]
+    }
+}
+
+/// How the displayed value range is chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clipping {
+    /// Clip to the `100 - p` and `p` percentiles of the section, e.g. 99
+    Percentile(f64),
+    /// Clip to plus/minus the `p` percentile of absolute values, keeping zero at the centre
+    Symmetric(f64),
+    /// Clip to a fixed `(min, max)`
+    Range(f64, f64),
+}
+
+/// Display options for [`render_section`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// Colour lookup
+    pub colormap: Colormap,
+    /// Value range mapped onto the colormap
+    pub clipping: Clipping,
+    /// Automatic gain control window in samples along the section columns
+    pub agc_window: Option<usize>,
+    /// Decimate so that neither image side exceeds this many pixels
+    pub max_size: Option<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            colormap: Colormap::Grey,
+            clipping: Clipping::Symmetric(99.0),
+            agc_window: None,
+            max_size: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Grey display with symmetric 99th percentile clipping
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the colormap
+    pub fn with_colormap(mut self, colormap: Colormap) -> Self {
+        self.colormap = colormap;
+        self
+    }
+
+    /// Set the clipping
+    pub fn with_clipping(mut self, clipping: Clipping) -> Self {
+        self.clipping = clipping;
+        self
+    }
+
+    /// Apply automatic gain control over `window` samples before clipping
+    pub fn with_agc(mut self, window: usize) -> Self {
+        self.agc_window = Some(window);
+        self
+    }
+
+    /// Limit the image to `max_size` pixels on its longest side
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+}
+
+/// An 8-bit RGBA image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major RGBA pixels, top row first
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// RGBA of the pixel at column `x` and row `y`
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * self.width + x) * 4;
+        [
+            self.pixels[offset],
+            self.pixels[offset + 1],
+            self.pixels[offset + 2],
+            self.pixels[offset + 3],
+        ]
+    }
+
+    /// Encode as PNG
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .map_err(|e| VdsError::Serialization(format!("PNG encoding failed: {}", e)))?;
+        Ok(png)
+    }
+}
+
+/// Divide each sample by the RMS of the `window` samples centred on it along axis 1
+///
+/// Non-finite samples are left unchanged and do not contribute to the RMS.
+pub fn apply_agc(data: &Array2<f32>, window: usize) -> Array2<f32> {
+    let mut gained = data.clone();
+    let half = window.max(1) / 2;
+    for mut trace in gained.axis_iter_mut(Axis(0)) {
+        let n = trace.len();
+        let mut energy = vec![0.0f64; n + 1];
+        let mut live = vec![0usize; n + 1];
+        for (i, &value) in trace.iter().enumerate() {
+            let finite = value.is_finite();
+            energy[i + 1] = energy[i] + if finite { (value as f64).powi(2) } else { 0.0 };
+            live[i + 1] = live[i] + finite as usize;
+        }
+        for (i, value) in trace.iter_mut().enumerate() {
+            let (start, end) = (i.saturating_sub(half), (i + half + 1).min(n));
+            let count = live[end] - live[start];
+            if !value.is_finite() || count == 0 {
+                continue;
+            }
+            let rms = ((energy[end] - energy[start]) / count as f64).sqrt();
+            *value = if rms > 0.0 {
+                (*value as f64 / rms) as f32
+            } else {
+                0.0
+            };
+        }
+    }
+    gained
+}
+
+/// Value at percentile `p` (0-100) of sorted values, interpolating between neighbours
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let position = (p.clamp(0.0, 100.0) / 100.0) * (sorted.len() - 1) as f64;
+    let (i, frac) = (position.floor() as usize, position.fract());
+    match sorted.get(i + 1) {
+        Some(next) => sorted[i] + frac * (next - sorted[i]),
+        None => sorted[i],
+    }
+}
+
+/// Render a section to an RGBA image
+pub fn render_section(section: &Section, options: &RenderOptions) -> Result<Image> {
+    let data = match options.agc_window {
+        Some(window) => apply_agc(&section.data, window),
+        None => section.data.clone(),
+    };
+    let (rows, columns) = data.dim();
+    if rows == 0 || columns == 0 {
+        return Err(VdsError::InvalidDimensions(
+            "Cannot render an empty section".to_string(),
+        ));
+    }
+
+    let mut finite: Vec<f64> = data
+        .iter()
+        .filter(|v| v.is_finite())
+        .map(|&v| v as f64)
+        .collect();
+    let (low, high) = match options.clipping {
+        Clipping::Range(min, max) => (min, max),
+        _ if finite.is_empty() => (0.0, 0.0),
+        Clipping::Percentile(p) => {
+            finite.sort_by(f64::total_cmp);
+            let p = p.max(100.0 - p);
+            (percentile(&finite, 100.0 - p), percentile(&finite, p))
+        }
+        Clipping::Symmetric(p) => {
+            finite.iter_mut().for_each(|v| *v = v.abs());
+            finite.sort_by(f64::total_cmp);
+            let clip = percentile(&finite, p);
+            (-clip, clip)
+        }
+    };
+
+    let (mut width, mut height) = (rows, columns);
+    if let Some(max_size) = options.max_size {
+        let scale = (max_size.max(1) as f64 / rows.max(columns) as f64).min(1.0);
+        width = ((rows as f64 * scale).round() as usize).max(1);
+        height = ((columns as f64 * scale).round() as usize).max(1);
+    }
+
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let column = y * columns / height;
+        for x in 0..width {
+            let value = data[[x * rows / width, column]] as f64;
+            let color = if !value.is_finite() {
+                [0, 0, 0, 0]
+            } else if high > low {
+                options.colormap.color((value - low) / (high - low))
+            } else {
+                options.colormap.color(0.5)
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+impl Section {
+    /// Render to an RGBA image
+    pub fn render(&self, options: &RenderOptions) -> Result<Image> {
+        render_section(self, options)
+    }
+}
+
+impl VolumeDataAccess {
+    /// PNG preview of the middle inline, e.g. for catalogue thumbnails
+    pub async fn render_thumbnail(&self, options: &RenderOptions) -> Result<Vec<u8>> {
+        let layout = self.layout();
+        let inline_axis = &layout.axes[layout.seismic_axes()?.inline];
+        let inline = inline_axis.index_to_coord(inline_axis.num_samples / 2);
+        self.read_inline(inline).await?.render(options)?.to_png()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::metadata::VdsMetadata;
+    use crate::types::{AxisDescriptor, DataType};
+    use crate::utils::typed_data_to_bytes;
+    use ndarray::array;
+    use tempfile::TempDir;
+
+    fn section(data: Array2<f32>) -> Section {
+        let (rows, columns) = data.dim();
+        Section {
+            data,
+            row_axis: "Crossline".to_string(),
+            column_axis: "Time".to_string(),
+            row_coords: (0..rows).map(|i| i as f64).collect(),
+            column_coords: (0..columns).map(|i| i as f64 * 4.0).collect(),
+        }
+    }
+
+    #[test]
+    fn test_render_section() {
+        let section = section(array![[-2.0, 0.0, f32::NAN], [1.0, 4.0, 2.0]]);
+
+        let options = RenderOptions::new().with_clipping(Clipping::Range(-2.0, 2.0));
+        let image = section.render(&options).unwrap();
+        assert_eq!((image.width, image.height), (2, 3));
+        assert_eq!(image.pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(image.pixel(0, 1), [128, 128, 128, 255]);
+        assert_eq!(image.pixel(1, 1), [255, 255, 255, 255]);
+        assert_eq!(image.pixel(0, 2)[3], 0);
+
+        // Symmetric clipping at the largest absolute value keeps zero white
+        let options = RenderOptions::new()
+            .with_colormap(Colormap::Seismic)
+            .with_clipping(Clipping::Symmetric(100.0));
+        let image = section.render(&options).unwrap();
+        assert_eq!(image.pixel(0, 1), [255, 255, 255, 255]);
+        assert_eq!(image.pixel(1, 1), [128, 0, 0, 255]);
+        assert_eq!(image.pixel(0, 0), [0, 0, 255, 255]);
+
+        let options = RenderOptions::new().with_clipping(Clipping::Percentile(100.0));
+        let image = section.render(&options).unwrap();
+        assert_eq!(image.pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 1), [255, 255, 255, 255]);
+        assert_eq!(Colormap::RedWhiteBlue.color(0.25), [255, 128, 128, 255]);
+    }
+
+    #[test]
+    fn test_apply_agc() {
+        let data = array![[1.0, -1.0, 1.0, 100.0, -100.0, 100.0]];
+        let gained = apply_agc(&data, 3);
+        assert_eq!(gained[[0, 0]], 1.0);
+        assert_eq!(gained[[0, 5]], 1.0);
+        // The window straddling the jump is dominated by the strong samples
+        assert!(gained[[0, 2]] < 0.02);
+    }
+
+    #[tokio::test]
+    async fn test_render_thumbnail() {
+        let dir = TempDir::new().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(3, "Inline", "unitless", 1.0, 3.0),
+            AxisDescriptor::new(40, "Crossline", "unitless", 1.0, 40.0),
+            AxisDescriptor::new(20, "Time", "ms", 0.0, 76.0),
+        ];
+        let layout = VolumeDataLayout::new(3, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([2, 16, 16, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(dir.path().to_str().unwrap(), VdsMetadata::new(layout))
+            .await
+            .unwrap();
+        let values: Vec<f32> = (0..3 * 40 * 20).map(|i| (i as f32 * 0.1).sin()).collect();
+        vds.write_slice(&[0, 0, 0], &[3, 40, 20], &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+
+        let options = RenderOptions::new().with_agc(5).with_max_size(10);
+        let png = vds.render_thumbnail(&options).await.unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (10, 5));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+    }
+}
@@ -0,0 +1,138 @@
+//! Interpolation of trace samples at fractional positions
+
+/// Interpolation along the sample (time/depth) axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleInterpolation {
+    /// Nearest sample
+    Nearest,
+    /// Linear between the two surrounding samples
+    Linear,
+    /// Cubic convolution (Catmull-Rom) over four samples
+    Cubic,
+    /// Band-limited sinc with an 8-tap Lanczos window
+    Sinc,
+}
+
+impl SampleInterpolation {
+    /// Number of samples needed on each side of a position
+    pub fn half_width(&self) -> usize {
+        match self {
+            SampleInterpolation::Nearest => 0,
+            SampleInterpolation::Linear => 1,
+            SampleInterpolation::Cubic => 2,
+            SampleInterpolation::Sinc => SINC_HALF_WIDTH,
+        }
+    }
+}
+
+/// Half width of the Lanczos window in samples
+pub(crate) const SINC_HALF_WIDTH: usize = 4;
+
+/// Interpolate `trace` at the fractional sample `position`
+///
+/// Samples beyond the ends of the trace are clamped to the first/last sample. Returns NaN for
+/// an empty trace or a non-finite position.
+pub fn interpolate(trace: &[f32], position: f64, method: SampleInterpolation) -> f32 {
+    if trace.is_empty() || !position.is_finite() {
+        return f32::NAN;
+    }
+
+    let last = trace.len() as isize - 1;
+    let sample = |i: isize| trace[i.clamp(0, last) as usize] as f64;
+    let base = position.floor();
+    let frac = position - base;
+    let i0 = base as isize;
+
+    let value = match method {
+        SampleInterpolation::Nearest => sample(position.round() as isize),
+        SampleInterpolation::Linear => sample(i0) * (1.0 - frac) + sample(i0 + 1) * frac,
+        SampleInterpolation::Cubic => {
+            let (p0, p1, p2, p3) = (sample(i0 - 1), sample(i0), sample(i0 + 1), sample(i0 + 2));
+            p1 + 0.5
+                * frac
+                * (p2 - p0
+                    + frac
+                        * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3
+                            + frac * (3.0 * (p1 - p2) + p3 - p0)))
+        }
+        SampleInterpolation::Sinc => return lowpass_sinc(trace, position, 1.0),
+    };
+
+    value as f32
+}
+
+/// Band-limited interpolation of `trace` at `position` with a cutoff below Nyquist
+///
+/// `cutoff` is a fraction of the input Nyquist frequency in `(0, 1]`; use the ratio of input to
+/// output sample interval when resampling to a coarser interval, so the Lanczos kernel widens to
+/// act as an anti-alias filter. A cutoff of 1 is [`SampleInterpolation::Sinc`].
+pub fn lowpass_sinc(trace: &[f32], position: f64, cutoff: f64) -> f32 {
+    if trace.is_empty() || !position.is_finite() {
+        return f32::NAN;
+    }
+
+    let last = trace.len() as isize - 1;
+    let base = position.floor();
+    if cutoff >= 1.0 && position == base {
+        return trace[(base as isize).clamp(0, last) as usize];
+    }
+
+    let cutoff = cutoff.clamp(f64::EPSILON, 1.0);
+    let half = (SINC_HALF_WIDTH as f64 / cutoff).ceil() as isize;
+    let i0 = base as isize;
+    let (mut sum, mut weights) = (0.0, 0.0);
+    for i in (i0 - half + 1)..=(i0 + half) {
+        let weight = lanczos((position - i as f64) * cutoff, SINC_HALF_WIDTH as f64);
+        sum += weight * trace[i.clamp(0, last) as usize] as f64;
+        weights += weight;
+    }
+    // Normalise so constant traces stay constant
+    (sum / weights) as f32
+}
+
+/// Lanczos window: `sinc(x) * sinc(x / a)` for `|x| < a`
+pub(crate) fn lanczos(x: f64, a: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else if x.abs() >= a {
+        0.0
+    } else {
+        let px = std::f64::consts::PI * x;
+        a * px.sin() * (px / a).sin() / (px * px)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_on_samples() {
+        let trace = [1.0f32, -2.0, 3.0, 0.5, 4.0];
+        for method in [
+            SampleInterpolation::Nearest,
+            SampleInterpolation::Linear,
+            SampleInterpolation::Cubic,
+            SampleInterpolation::Sinc,
+        ] {
+            for (i, &v) in trace.iter().enumerate() {
+                assert_eq!(interpolate(&trace, i as f64, method), v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_between_samples() {
+        let ramp: Vec<f32> = (0..16).map(|i| i as f32 * 2.0).collect();
+        assert_eq!(interpolate(&ramp, 3.4, SampleInterpolation::Nearest), 6.0);
+        assert!((interpolate(&ramp, 3.25, SampleInterpolation::Linear) - 6.5).abs() < 1e-6);
+        // Catmull-Rom reproduces linear data exactly
+        assert!((interpolate(&ramp, 7.6, SampleInterpolation::Cubic) - 15.2).abs() < 1e-5);
+        // Windowed sinc is close on smooth data away from the ends
+        assert!((interpolate(&ramp, 7.5, SampleInterpolation::Sinc) - 15.0).abs() < 1e-3);
+
+        let constant = [3.0f32; 10];
+        assert!((interpolate(&constant, 0.3, SampleInterpolation::Sinc) - 3.0).abs() < 1e-6);
+        assert!(interpolate(&[], 0.0, SampleInterpolation::Linear).is_nan());
+    }
+}
@@ -58,6 +58,20 @@ impl Default for BrickSize {
     }
 }
 
+/// Dimension indices of the inline, crossline and sample axes of a seismic volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeismicAxes {
+    /// Dimension of the inline axis
+    pub inline: usize,
+    /// Dimension of the crossline axis
+    pub crossline: usize,
+    /// Dimension of the sample (time/depth) axis
+    pub sample: usize,
+}
+
+/// Axis names recognised as the vertical (sample) axis of a seismic volume
+pub const SAMPLE_AXIS_NAMES: &[&str] = &["Sample", "Time", "Depth"];
+
 /// Layout of volume data - describes how the volume is organized
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VolumeDataLayout {
@@ -136,11 +150,67 @@ impl VolumeDataLayout {
         self
     }
 
+    /// Layout of a level of detail, where each dimension is decimated by `2^lod`
+    ///
+    /// The brick size is unchanged, so a LOD level has fewer bricks.
+    pub fn lod_layout(&self, lod: usize) -> Result<Self> {
+        if lod >= self.lod_levels {
+            return Err(VdsError::OutOfBounds(format!(
+                "LOD {} out of range ({} levels)",
+                lod, self.lod_levels
+            )));
+        }
+
+        let factor = 1usize << lod;
+        let mut layout = self.clone();
+        layout.lod_levels = self.lod_levels - lod;
+        for axis in &mut layout.axes {
+            let num_samples = axis.num_samples.div_ceil(factor);
+            axis.coord_max = axis.index_to_coord(num_samples.saturating_sub(1) * factor);
+            axis.num_samples = num_samples;
+        }
+        Ok(layout)
+    }
+
     /// Get the total size in each dimension
     pub fn size(&self) -> Vec<usize> {
         self.axes.iter().map(|a| a.num_samples).collect()
     }
 
+    /// Find the dimension whose axis has the given name (case-insensitive)
+    pub fn axis_index(&self, name: &str) -> Option<usize> {
+        self.axes
+            .iter()
+            .position(|axis| axis.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Resolve the inline, crossline and sample dimensions by axis name
+    pub fn seismic_axes(&self) -> Result<SeismicAxes> {
+        let find = |name: &str| {
+            self.axis_index(name)
+                .ok_or_else(|| VdsError::InvalidDimensions(format!("Volume has no {} axis", name)))
+        };
+
+        Ok(SeismicAxes {
+            inline: find("Inline")?,
+            crossline: find("Crossline")?,
+            sample: self.sample_axis_index()?,
+        })
+    }
+
+    /// Resolve the sample (time/depth) dimension by axis name
+    pub fn sample_axis_index(&self) -> Result<usize> {
+        SAMPLE_AXIS_NAMES
+            .iter()
+            .find_map(|name| self.axis_index(name))
+            .ok_or_else(|| {
+                VdsError::InvalidDimensions(format!(
+                    "Volume has no sample axis (expected one of {:?})",
+                    SAMPLE_AXIS_NAMES
+                ))
+            })
+    }
+
     /// Get the number of bricks in each dimension
     pub fn brick_count(&self) -> Vec<usize> {
         self.axes
@@ -200,6 +270,16 @@ impl VolumeDataLayout {
             .collect()
     }
 
+    /// Uncompressed size in bytes of the brick at `index`, smaller than
+    /// [`brick_size_bytes`](Self::brick_size_bytes) at the volume edges
+    pub fn brick_data_bytes(&self, index: usize) -> usize {
+        self.brick_data_range(&self.brick_index_to_coords(index))
+            .iter()
+            .map(|(min, max)| max - min)
+            .product::<usize>()
+            * self.data_type.size_in_bytes()
+    }
+
     /// Calculate the size in bytes of a single brick
     pub fn brick_size_bytes(&self) -> usize {
         self.brick_size.total_voxels() * self.data_type.size_in_bytes()
@@ -302,6 +382,38 @@ mod tests {
         assert_eq!(range, vec![(960, 1000), (768, 800), (448, 500)]);
     }
 
+    #[test]
+    fn test_seismic_axes() {
+        let layout = create_test_layout();
+        let axes = layout.seismic_axes().unwrap();
+        assert_eq!(
+            axes,
+            SeismicAxes {
+                inline: 0,
+                crossline: 1,
+                sample: 2
+            }
+        );
+        assert_eq!(layout.axis_index("crossline"), Some(1));
+
+        let axes = vec![
+            AxisDescriptor::new(10, "X", "m", 0.0, 9.0),
+            AxisDescriptor::new(10, "Y", "m", 0.0, 9.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes).unwrap();
+        assert!(layout.seismic_axes().is_err());
+    }
+
+    #[test]
+    fn test_lod_layout() {
+        let layout = create_test_layout().with_lod_levels(3);
+        let lod2 = layout.lod_layout(2).unwrap();
+        assert_eq!(lod2.size(), vec![250, 200, 125]);
+        assert_eq!(lod2.brick_count(), vec![4, 4, 2]);
+        assert!((lod2.axes[2].step_size() - layout.axes[2].step_size() * 4.0).abs() < 1e-9);
+        assert!(layout.lod_layout(3).is_err());
+    }
+
     #[test]
     fn test_is_in_bounds() {
         let layout = create_test_layout();
@@ -10,6 +10,7 @@
 //! - Local filesystem backend (implement IOManager trait for cloud storage)
 //! - Chunked/bricked data layout for efficient random access
 //! - Async I/O throughout
+//! - SEG-Y export (rev 1 / rev 2, IEEE or IBM floats) and IBM float channels
 //!
 //! # Cloud Storage
 //!
@@ -34,13 +35,31 @@
 //! ```
 
 pub mod access;
+pub mod attributes;
+pub mod brick;
+pub mod compare;
 pub mod compression;
 pub mod error;
+pub mod filter;
+pub mod histogram;
+pub mod horizon;
+pub mod ibm;
+pub mod imaging;
+pub mod interpolation;
 pub mod io;
 pub mod layout;
 pub mod metadata;
+pub mod processor;
+pub mod random_line;
+pub mod resample;
+pub mod section;
+pub mod segy;
+pub mod spectrum;
+pub mod statistics;
+pub mod subvolume;
 pub mod types;
 pub mod utils;
+pub mod value_index;
 
 // Re-exports
 pub use access::VolumeDataAccess;
@@ -49,6 +68,8 @@ pub use error::{Result, VdsError};
 pub use io::{IOManager, StorageBackend};
 pub use layout::{BrickSize, VolumeDataLayout};
 pub use metadata::VdsMetadata;
+pub use section::Section;
+pub use segy::SegyExporter;
 pub use types::{AxisDescriptor, DataType, Dimension};
 
 /// Version of the OpenVDS implementation
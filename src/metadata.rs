@@ -1,11 +1,19 @@
 //! VDS metadata structures
 
-use crate::compression::CompressionMethod;
+use crate::compression::{
+    is_constant, AdaptiveCompression, CompressionMethod, CompressionParams, Compressor,
+    CompressorRegistry, ConstantCompressor, DeflateCompressor, ElementRLECompressor, LZ4Compressor,
+    QuantizedCompressor, ZstdCompressor,
+};
+use crate::error::{Result, VdsError};
+use crate::filter::{Filter, FilteredCompressor};
+use crate::histogram::Histogram;
 use crate::layout::VolumeDataLayout;
-use crate::types::ValueRange;
+use crate::types::{DataType, ValueRange};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// VDS file format version
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -15,7 +23,10 @@ pub struct VdsVersion {
 }
 
 impl VdsVersion {
-    pub const CURRENT: Self = Self { major: 3, minor: 0 };
+    pub const CURRENT: Self = Self { major: 3, minor: 1 };
+
+    /// First version whose bricks start with a [`BrickHeader`](crate::brick::BrickHeader)
+    pub const BRICK_HEADERS: Self = Self { major: 3, minor: 1 };
 
     pub fn new(major: u16, minor: u16) -> Self {
         Self { major, minor }
@@ -24,6 +35,11 @@ impl VdsVersion {
     pub fn is_compatible(&self, other: &Self) -> bool {
         self.major == other.major
     }
+
+    /// Whether bricks of this version must carry a header
+    pub fn has_brick_headers(&self) -> bool {
+        (self.major, self.minor) >= (Self::BRICK_HEADERS.major, Self::BRICK_HEADERS.minor)
+    }
 }
 
 impl Default for VdsVersion {
@@ -44,9 +60,38 @@ pub struct VdsMetadata {
     /// Compression method used for bricks
     pub compression: CompressionMethod,
 
+    /// Codec parameters bricks are compressed with
+    #[serde(default)]
+    pub compression_params: CompressionParams,
+
+    /// Per-brick codec selection; when set it replaces `compression` for writing bricks
+    #[serde(default)]
+    pub adaptive_compression: Option<AdaptiveCompression>,
+
+    /// Filters applied to bricks before compression, in encoding order
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+
+    /// Whether Zstd bricks use the dictionary stored at
+    /// [`ZSTD_DICTIONARY_PATH`](crate::compression::ZSTD_DICTIONARY_PATH)
+    #[serde(default)]
+    pub zstd_dictionary: bool,
+
     /// Compression tolerance (for lossy compression)
+    ///
+    /// Maximum deviation from the original value, in steps of the value range divided into 255,
+    /// as in OpenVDS. Zero keeps compression lossless.
     pub compression_tolerance: f32,
 
+    /// Whether lossy codecs may be used; when false, lossy methods store bricks losslessly
+    #[serde(default = "allow_lossy_default")]
+    pub allow_lossy_compression: bool,
+
+    /// Absolute error bound for lossy codecs, fixed from the tolerance and value range when the
+    /// volume is created
+    #[serde(default)]
+    pub lossy_error_bound: Option<f64>,
+
     /// Value range of the data
     pub value_range: ValueRange,
 
@@ -61,6 +106,18 @@ pub struct VdsMetadata {
 
     /// Survey/acquisition metadata (for seismic data)
     pub survey_metadata: Option<SurveyMetadata>,
+
+    /// Sample statistics from the last statistics pass
+    #[serde(default)]
+    pub statistics: Option<VolumeStatistics>,
+
+    /// Cached histograms, keyed by the options they were computed with
+    #[serde(default)]
+    pub histograms: HashMap<String, Histogram>,
+}
+
+fn allow_lossy_default() -> bool {
+    true
 }
 
 impl VdsMetadata {
@@ -71,12 +128,39 @@ impl VdsMetadata {
             version: VdsVersion::default(),
             layout,
             compression: CompressionMethod::Zstd,
+            compression_params: CompressionParams::default(),
+            adaptive_compression: None,
+            filters: Vec::new(),
+            zstd_dictionary: false,
             compression_tolerance: 0.0,
+            allow_lossy_compression: true,
+            lossy_error_bound: None,
             value_range: ValueRange::new(0.0, 0.0),
             created_at: now,
             modified_at: now,
             custom_metadata: HashMap::new(),
             survey_metadata: None,
+            statistics: None,
+            histograms: HashMap::new(),
+        }
+    }
+
+    /// Metadata for a new volume with `layout` that is compressed like this one
+    ///
+    /// Keeps the codec, its parameters, filters, adaptive selection, lossy settings, zstd
+    /// dictionary use and survey metadata; everything describing the samples starts fresh.
+    pub fn derived(&self, layout: VolumeDataLayout) -> Self {
+        Self {
+            compression: self.compression,
+            compression_params: self.compression_params,
+            adaptive_compression: self.adaptive_compression.clone(),
+            filters: self.filters.clone(),
+            zstd_dictionary: self.zstd_dictionary,
+            compression_tolerance: self.compression_tolerance,
+            allow_lossy_compression: self.allow_lossy_compression,
+            lossy_error_bound: self.lossy_error_bound,
+            survey_metadata: self.survey_metadata.clone(),
+            ..Self::new(layout)
         }
     }
 
@@ -86,12 +170,186 @@ impl VdsMetadata {
         self
     }
 
+    /// Set the codec parameters
+    pub fn with_compression_params(mut self, params: CompressionParams) -> Self {
+        self.compression_params = params;
+        self
+    }
+
+    /// Compress each brick with several codecs and keep one
+    pub fn with_adaptive_compression(mut self, adaptive: AdaptiveCompression) -> Self {
+        self.adaptive_compression = Some(adaptive);
+        self
+    }
+
+    /// Set the filters applied to bricks before compression
+    pub fn with_filters(mut self, filters: Vec<Filter>) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Compressor for this volume's bricks: the compression method, configured with
+    /// [`compression_params`](Self::compression_params), behind the filter chain
+    ///
+    /// [`CompressionMethod::ElementRLE`] runs over elements of the volume's data type. F32
+    /// volumes using [`CompressionMethod::Quantized`] are compressed within
+    /// [`lossy_max_error`](Self::lossy_max_error); that codec models F32 samples itself, so
+    /// filters are not applied to it. [`CompressionMethod::Zstd`] uses `zstd_dictionary` when
+    /// [`zstd_dictionary`](Self::zstd_dictionary) is set. Other codecs come from the
+    /// [`CompressorRegistry`], failing if the method is not registered.
+    pub fn brick_compressor(
+        &self,
+        zstd_dictionary: Option<Arc<Vec<u8>>>,
+    ) -> Result<Box<dyn Compressor>> {
+        self.compressor_for(self.compression, zstd_dictionary)
+    }
+
+    /// Compressor for bricks stored with `method`, configured like
+    /// [`brick_compressor`](Self::brick_compressor)
+    pub fn compressor_for(
+        &self,
+        method: CompressionMethod,
+        zstd_dictionary: Option<Arc<Vec<u8>>>,
+    ) -> Result<Box<dyn Compressor>> {
+        let params = &self.compression_params;
+        let element_size = self.layout.data_type.size_in_bytes();
+        if method == CompressionMethod::Constant {
+            return Ok(Box::new(ConstantCompressor::new(element_size)));
+        }
+        if method == CompressionMethod::Quantized {
+            let compressor = match (self.layout.data_type, self.lossy_max_error()) {
+                (DataType::F32, Some(max_error)) => QuantizedCompressor::new(max_error),
+                _ => QuantizedCompressor::lossless(),
+            };
+            return Ok(Box::new(compressor.with_zstd_params(params.zstd)));
+        }
+        let compressor: Box<dyn Compressor> = match method {
+            CompressionMethod::Deflate => Box::new(DeflateCompressor::new(params.deflate)),
+            CompressionMethod::ElementRLE => Box::new(ElementRLECompressor::new(element_size)),
+            CompressionMethod::Zstd => {
+                let compressor = ZstdCompressor::new().with_params(params.zstd);
+                match zstd_dictionary.filter(|_| self.zstd_dictionary) {
+                    Some(dictionary) => Box::new(compressor.with_dictionary(dictionary)),
+                    None => Box::new(compressor),
+                }
+            }
+            CompressionMethod::LZ4 => Box::new(LZ4Compressor::with_params(params.lz4)),
+            CompressionMethod::LZ4HC => Box::new(LZ4Compressor::with_hc_params(params.lz4hc)),
+            method => CompressorRegistry::global().create(method)?,
+        };
+        Ok(if self.filters.is_empty() {
+            compressor
+        } else {
+            Box::new(FilteredCompressor::new(self.filters.clone(), compressor))
+        })
+    }
+
+    /// Check the codec parameters and that every codec the volume writes with is registered
+    pub fn validate_compression(&self) -> Result<()> {
+        self.compression_params.validate()?;
+        let registry = CompressorRegistry::global();
+        let mut methods = vec![self.compression];
+        if let Some(adaptive) = &self.adaptive_compression {
+            adaptive.validate()?;
+            methods.extend(&adaptive.candidates);
+        }
+        match methods.into_iter().find(|&m| !registry.is_registered(m)) {
+            Some(method) => Err(VdsError::UnregisteredCodec(method.id())),
+            None => Ok(()),
+        }
+    }
+
+    /// Compress a brick for writing, returning the codec used and the payload
+    ///
+    /// Bricks whose elements are all equal are stored as [`CompressionMethod::Constant`].
+    /// Other bricks use [`adaptive_compression`](Self::adaptive_compression) when set, and
+    /// [`brick_compressor`](Self::brick_compressor) otherwise.
+    pub fn compress_brick(
+        &self,
+        data: &[u8],
+        zstd_dictionary: Option<Arc<Vec<u8>>>,
+    ) -> Result<(CompressionMethod, Vec<u8>)> {
+        if is_constant(data, self.layout.data_type.size_in_bytes()) {
+            let compressor = self.compressor_for(CompressionMethod::Constant, None)?;
+            return Ok((CompressionMethod::Constant, compressor.compress(data)?));
+        }
+        match &self.adaptive_compression {
+            Some(adaptive) => adaptive.compress(data, |method| {
+                self.compressor_for(method, zstd_dictionary.clone())
+            }),
+            None => {
+                let compressor = self.brick_compressor(zstd_dictionary)?;
+                Ok((compressor.method(), compressor.compress(data)?))
+            }
+        }
+    }
+
     /// Set compression tolerance
     pub fn with_compression_tolerance(mut self, tolerance: f32) -> Self {
         self.compression_tolerance = tolerance;
         self
     }
 
+    /// Allow or forbid lossy compression
+    pub fn with_allow_lossy_compression(mut self, allow: bool) -> Self {
+        self.allow_lossy_compression = allow;
+        self
+    }
+
+    /// Maximum absolute error allowed for lossy compression
+    ///
+    /// The [`lossy_error_bound`](Self::lossy_error_bound) fixed at creation, so the bound does
+    /// not follow the value range as bricks are written; derived from the tolerance and value
+    /// range until then. `None` when lossy compression is forbidden or the bound is zero.
+    pub fn lossy_max_error(&self) -> Option<f64> {
+        let max_error = self
+            .lossy_error_bound
+            .unwrap_or_else(|| self.range_error_bound());
+        (self.allow_lossy_compression && max_error > 0.0).then_some(max_error)
+    }
+
+    /// Fix [`lossy_error_bound`](Self::lossy_error_bound) from the tolerance and value range,
+    /// unless it is already set
+    ///
+    /// Fails when an F32 volume may be compressed lossily with a non-zero tolerance but has no
+    /// value range to derive the bound from.
+    pub fn fix_lossy_error_bound(&mut self) -> Result<()> {
+        if self.lossy_error_bound.is_some() {
+            return Ok(());
+        }
+        let quantized = self.compression == CompressionMethod::Quantized
+            || self.adaptive_compression.as_ref().is_some_and(|adaptive| {
+                adaptive.candidates.contains(&CompressionMethod::Quantized)
+            });
+        if quantized
+            && self.layout.data_type == DataType::F32
+            && self.allow_lossy_compression
+            && self.compression_tolerance > 0.0
+            && !self.has_value_range()
+        {
+            return Err(VdsError::Configuration(format!(
+                "Lossy compression with tolerance {} needs a value range",
+                self.compression_tolerance
+            )));
+        }
+        self.lossy_error_bound = Some(self.range_error_bound());
+        Ok(())
+    }
+
+    /// Error bound derived from the tolerance and value range; zero for an invalid range
+    fn range_error_bound(&self) -> f64 {
+        if !self.value_range.is_valid() {
+            return 0.0;
+        }
+        let width = self.value_range.max - self.value_range.min;
+        (self.compression_tolerance as f64 * width / 255.0).max(0.0)
+    }
+
+    /// Whether a value range has been set; the default `0..0` counts as unset
+    pub fn has_value_range(&self) -> bool {
+        self.value_range != ValueRange::new(0.0, 0.0)
+    }
+
     /// Set value range
     pub fn with_value_range(mut self, range: ValueRange) -> Self {
         self.value_range = range;
@@ -120,6 +378,36 @@ impl VdsMetadata {
     }
 }
 
+/// Sample statistics of a whole volume
+///
+/// Moments are taken over finite samples only; NaN and infinite samples are counted separately.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VolumeStatistics {
+    /// Number of finite samples
+    pub count: u64,
+
+    /// Number of NaN samples
+    pub nan_count: u64,
+
+    /// Number of infinite samples
+    pub inf_count: u64,
+
+    /// Smallest finite sample
+    pub min: f64,
+
+    /// Largest finite sample
+    pub max: f64,
+
+    /// Mean of the finite samples
+    pub mean: f64,
+
+    /// Population standard deviation of the finite samples
+    pub std_dev: f64,
+
+    /// Root mean square of the finite samples
+    pub rms: f64,
+}
+
 /// Survey/acquisition metadata for seismic data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SurveyMetadata {
@@ -141,10 +429,81 @@ pub struct SurveyMetadata {
     /// Geographic coordinate system
     pub coordinate_system: Option<String>,
 
+    /// Mapping from inline/crossline indices to world XY coordinates
+    #[serde(default)]
+    pub transform: Option<SurveyTransform>,
+
     /// SEG-Y specific metadata
     pub segy_metadata: Option<SegyMetadata>,
 }
 
+impl SurveyMetadata {
+    pub fn new(survey_name: impl Into<String>, survey_type: impl Into<String>) -> Self {
+        Self {
+            survey_name: survey_name.into(),
+            survey_type: survey_type.into(),
+            acquisition_date: None,
+            processing_date: None,
+            company: None,
+            coordinate_system: None,
+            transform: None,
+            segy_metadata: None,
+        }
+    }
+}
+
+/// Affine mapping from inline/crossline sample indices to world XY coordinates
+///
+/// `world = origin + inline_index * inline_spacing + crossline_index * crossline_spacing`,
+/// where the indices count samples along the volume's `Inline` and `Crossline` axes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SurveyTransform {
+    /// World XY of the first inline/crossline sample
+    pub origin: [f64; 2],
+
+    /// World XY step per inline sample
+    pub inline_spacing: [f64; 2],
+
+    /// World XY step per crossline sample
+    pub crossline_spacing: [f64; 2],
+}
+
+impl SurveyTransform {
+    pub fn new(origin: [f64; 2], inline_spacing: [f64; 2], crossline_spacing: [f64; 2]) -> Self {
+        Self {
+            origin,
+            inline_spacing,
+            crossline_spacing,
+        }
+    }
+
+    /// World XY of a (possibly fractional) inline/crossline index
+    pub fn index_to_world(&self, inline_index: f64, crossline_index: f64) -> [f64; 2] {
+        [
+            self.origin[0]
+                + inline_index * self.inline_spacing[0]
+                + crossline_index * self.crossline_spacing[0],
+            self.origin[1]
+                + inline_index * self.inline_spacing[1]
+                + crossline_index * self.crossline_spacing[1],
+        ]
+    }
+
+    /// Fractional inline/crossline index of a world XY position, or `None` if the spacing
+    /// vectors are parallel
+    pub fn world_to_index(&self, x: f64, y: f64) -> Option<[f64; 2]> {
+        let [a, c] = self.inline_spacing;
+        let [b, d] = self.crossline_spacing;
+        let det = a * d - b * c;
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+        let dx = x - self.origin[0];
+        let dy = y - self.origin[1];
+        Some([(d * dx - b * dy) / det, (a * dy - c * dx) / det])
+    }
+}
+
 /// SEG-Y specific metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SegyMetadata {
@@ -245,6 +604,53 @@ mod tests {
 
         metadata.add_metadata("project", "North Sea Survey");
         assert_eq!(metadata.get_metadata("project"), Some("North Sea Survey"));
+
+        assert_eq!(metadata.lossy_max_error(), None);
+        metadata = metadata.with_compression_tolerance(2.55);
+        assert!((metadata.lossy_max_error().unwrap() - 20.0).abs() < 1e-3);
+        metadata = metadata.with_allow_lossy_compression(false);
+        assert_eq!(metadata.lossy_max_error(), None);
+
+        // Metadata written before codec parameters were recorded uses the defaults
+        let mut json = serde_json::to_value(&metadata).unwrap();
+        json.as_object_mut().unwrap().remove("compression_params");
+        let restored: VdsMetadata = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.compression_params, CompressionParams::default());
+    }
+
+    #[test]
+    fn test_derived_metadata() {
+        let axes = vec![AxisDescriptor::new(100, "Sample", "ms", 0.0, 396.0)];
+        let layout = VolumeDataLayout::new(1, DataType::I16, axes).unwrap();
+        let mut source = VdsMetadata::new(layout.clone())
+            .with_compression(CompressionMethod::LZ4HC)
+            .with_filters(vec![Filter::Delta(2)])
+            .with_compression_tolerance(1.0)
+            .with_value_range(ValueRange::new(-5.0, 5.0));
+        source.zstd_dictionary = true;
+        source.add_metadata("attribute", "Envelope");
+
+        let mut output_layout = layout;
+        output_layout.data_type = DataType::F32;
+        let derived = source.derived(output_layout);
+        assert_eq!(derived.layout.data_type, DataType::F32);
+        assert_eq!(derived.compression, CompressionMethod::LZ4HC);
+        assert_eq!(derived.filters, vec![Filter::Delta(2)]);
+        assert_eq!(derived.compression_tolerance, 1.0);
+        assert!(derived.zstd_dictionary);
+        assert_eq!(derived.value_range, ValueRange::new(0.0, 0.0));
+        assert!(derived.custom_metadata.is_empty());
+    }
+
+    #[test]
+    fn test_survey_transform() {
+        let transform = SurveyTransform::new([1000.0, 2000.0], [0.0, 12.5], [25.0, 0.0]);
+        assert_eq!(transform.index_to_world(0.0, 0.0), [1000.0, 2000.0]);
+        assert_eq!(transform.index_to_world(2.0, 4.0), [1100.0, 2025.0]);
+        assert_eq!(transform.world_to_index(1100.0, 2025.0), Some([2.0, 4.0]));
+
+        let degenerate = SurveyTransform::new([0.0, 0.0], [1.0, 1.0], [2.0, 2.0]);
+        assert_eq!(degenerate.world_to_index(1.0, 1.0), None);
     }
 
     #[test]
@@ -0,0 +1,386 @@
+//! Brick-parallel map/reduce processing
+//!
+//! [`VolumeProcessor`] reads each brick of a volume as `f32` samples, optionally extended by a
+//! halo of neighbouring voxels, and runs a closure on it on the blocking thread pool. Reads,
+//! computation and writes of up to `max_concurrency` bricks are in flight at once.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::metadata::VdsMetadata;
+use crate::utils::f32_to_bytes;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Default number of bricks in flight
+pub const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+/// Samples of one brick plus its halo
+#[derive(Debug, Clone)]
+pub struct BrickData {
+    /// Brick index in the grid being processed
+    pub index: usize,
+    /// Voxel range `[min, max)` of the brick in each dimension
+    pub range: Vec<(usize, usize)>,
+    /// Voxel range covered by `data`: the brick range extended by the halo, clipped to the volume
+    pub data_range: Vec<(usize, usize)>,
+    /// Samples of `data_range`, row-major with the last dimension fastest
+    pub data: Vec<f32>,
+}
+
+impl BrickData {
+    /// Size of `data` in each dimension
+    pub fn dims(&self) -> Vec<usize> {
+        self.data_range.iter().map(|(min, max)| max - min).collect()
+    }
+
+    /// Sample at volume voxel coordinates inside `data_range`
+    pub fn get(&self, coords: &[usize]) -> f32 {
+        let offset = coords
+            .iter()
+            .zip(&self.data_range)
+            .fold(0, |acc, (&c, &(min, max))| acc * (max - min) + (c - min));
+        self.data[offset]
+    }
+
+    /// Samples of the brick itself, without the halo
+    pub fn brick_samples(&self) -> Vec<f32> {
+        if self.range == self.data_range {
+            return self.data.clone();
+        }
+
+        let n = self.range.len();
+        let row = self.range[n - 1].1 - self.range[n - 1].0;
+        let mut samples =
+            Vec::with_capacity(self.range.iter().map(|(min, max)| max - min).product());
+        let mut coords: Vec<usize> = self.range.iter().map(|&(min, _)| min).collect();
+        loop {
+            let start = self
+                .data_range
+                .iter()
+                .zip(&coords)
+                .fold(0, |acc, (&(min, max), &c)| acc * (max - min) + (c - min));
+            samples.extend_from_slice(&self.data[start..start + row]);
+
+            // Advance to the next row, last-but-one dimension fastest
+            let mut dim = n - 1;
+            loop {
+                if dim == 0 {
+                    return samples;
+                }
+                dim -= 1;
+                coords[dim] += 1;
+                if coords[dim] < self.range[dim].1 {
+                    break;
+                }
+                coords[dim] = self.range[dim].0;
+            }
+        }
+    }
+}
+
+/// Progress of a processing job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessorProgress {
+    /// Bricks finished so far
+    pub completed: usize,
+    /// Bricks in the job
+    pub total: usize,
+}
+
+type ProgressCallback = Arc<dyn Fn(ProcessorProgress) + Send + Sync>;
+
+/// Runs per-brick closures over a volume with bounded parallelism
+pub struct VolumeProcessor<'a> {
+    input: &'a VolumeDataAccess,
+    halo: [usize; 6],
+    max_concurrency: usize,
+    progress: Option<ProgressCallback>,
+}
+
+impl<'a> VolumeProcessor<'a> {
+    /// Create a processor reading from `input`, without a halo
+    pub fn new(input: &'a VolumeDataAccess) -> Self {
+        Self {
+            input,
+            halo: [0; 6],
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            progress: None,
+        }
+    }
+
+    /// Extend each brick by `halo` voxels on both sides of every dimension
+    pub fn with_halo(mut self, halo: [usize; 6]) -> Self {
+        self.halo = halo;
+        self
+    }
+
+    /// Set the number of bricks in flight
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Report progress after each brick
+    pub fn with_progress(
+        mut self,
+        callback: impl Fn(ProcessorProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Write `transform` of each brick to a new volume at `output_url`
+    ///
+    /// The output layout in `metadata` may use a different brick size, data type and codec, but
+    /// must have the input's voxel dimensions. `transform` is called once per output brick with
+    /// the input samples of that brick plus the halo, and returns the brick's samples. The
+    /// output shares the input's zstd dictionary, if it has one.
+    pub async fn map<F>(
+        &self,
+        output_url: impl Into<String>,
+        metadata: VdsMetadata,
+        transform: F,
+    ) -> Result<VolumeDataAccess>
+    where
+        F: Fn(&BrickData) -> Result<Vec<f32>> + Send + Sync + 'static,
+    {
+        let input_size = self.input.layout().size();
+        if metadata.layout.size() != input_size {
+            return Err(VdsError::InvalidDimensions(format!(
+                "Output size {:?} does not match input size {:?}",
+                metadata.layout.size(),
+                input_size
+            )));
+        }
+
+        let output = self.input.create_derived(output_url, metadata).await?;
+        let layout = output.layout();
+        let total = layout.total_bricks();
+        let completed = AtomicUsize::new(0);
+        let transform = Arc::new(transform);
+
+        stream::iter(0..total)
+            .map(|index| {
+                let (layout, output, completed) = (&layout, &output, &completed);
+                let transform = transform.clone();
+                async move {
+                    let range = layout.brick_data_range(&layout.brick_index_to_coords(index));
+                    let expected: usize = range.iter().map(|(min, max)| max - min).product();
+                    let brick = self.read_brick_data(index, range).await?;
+
+                    let values = run_blocking(move || transform(&brick)).await?;
+                    if values.len() != expected {
+                        return Err(VdsError::InvalidDimensions(format!(
+                            "Brick {} transform returned {} samples, expected {}",
+                            index,
+                            values.len(),
+                            expected
+                        )));
+                    }
+                    output
+                        .write_brick(index, &f32_to_bytes(&values, layout.data_type))
+                        .await?;
+                    self.report(completed, total);
+                    Ok(())
+                }
+            })
+            .buffer_unordered(self.max_concurrency)
+            .try_collect::<Vec<()>>()
+            .await?;
+
+        Ok(output)
+    }
+
+    /// Map each input brick to a value and combine the values with `reduce`
+    ///
+    /// Bricks complete in any order, so `reduce` should be associative and commutative.
+    pub async fn reduce<T, M, R>(&self, init: T, map: M, mut reduce: R) -> Result<T>
+    where
+        T: Send + 'static,
+        M: Fn(&BrickData) -> Result<T> + Send + Sync + 'static,
+        R: FnMut(T, T) -> T,
+    {
+        let layout = self.input.layout();
+        let total = layout.total_bricks();
+        let completed = AtomicUsize::new(0);
+        let map = Arc::new(map);
+
+        let mut results = stream::iter(0..total)
+            .map(|index| {
+                let (layout, completed) = (&layout, &completed);
+                let map = map.clone();
+                async move {
+                    let range = layout.brick_data_range(&layout.brick_index_to_coords(index));
+                    let brick = self.read_brick_data(index, range).await?;
+                    let value = run_blocking(move || map(&brick)).await?;
+                    self.report(completed, total);
+                    Ok::<_, VdsError>(value)
+                }
+            })
+            .buffer_unordered(self.max_concurrency);
+
+        let mut accumulated = init;
+        while let Some(value) = results.try_next().await? {
+            accumulated = reduce(accumulated, value);
+        }
+        Ok(accumulated)
+    }
+
+    /// Read the input samples of a brick range extended by the halo
+    async fn read_brick_data(&self, index: usize, range: Vec<(usize, usize)>) -> Result<BrickData> {
+        let size = self.input.layout().size();
+        let data_range: Vec<(usize, usize)> = range
+            .iter()
+            .enumerate()
+            .map(|(dim, &(min, max))| {
+                let halo = self.halo.get(dim).copied().unwrap_or(0);
+                (min.saturating_sub(halo), (max + halo).min(size[dim]))
+            })
+            .collect();
+        let min_coords: Vec<usize> = data_range.iter().map(|&(min, _)| min).collect();
+        let max_coords: Vec<usize> = data_range.iter().map(|&(_, max)| max).collect();
+        let data = self.input.read_slice_f32(&min_coords, &max_coords).await?;
+
+        Ok(BrickData {
+            index,
+            range,
+            data_range,
+            data,
+        })
+    }
+
+    fn report(&self, completed: &AtomicUsize, total: usize) {
+        let completed = completed.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(callback) = &self.progress {
+            callback(ProcessorProgress { completed, total });
+        }
+    }
+}
+
+/// Run CPU-bound work on the blocking thread pool
+async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| VdsError::Processing(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::CompressionMethod;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::types::{AxisDescriptor, DataType};
+    use crate::utils::typed_data_to_bytes;
+    use tempfile::TempDir;
+
+    const SIZE: [usize; 2] = [9, 7];
+
+    async fn create_test_volume(dir: &TempDir) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(SIZE[0], "Inline", "unitless", 0.0, 8.0),
+            AxisDescriptor::new(SIZE[1], "Sample", "ms", 0.0, 24.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 4, 1, 1, 1, 1]));
+        let path = dir.path().join("input");
+        let vds = VolumeDataAccess::create(path.to_str().unwrap(), VdsMetadata::new(layout))
+            .await
+            .unwrap();
+        let values: Vec<f32> = (0..SIZE[0] * SIZE[1]).map(|v| (v % 11) as f32).collect();
+        vds.write_slice(&[0, 0], &SIZE, &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+        vds
+    }
+
+    #[tokio::test]
+    async fn test_map_with_halo() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+        let input = vds.read_slice_f32(&[0, 0], &SIZE).await.unwrap();
+
+        // Output with a different brick size, data type and codec
+        let mut layout = vds
+            .layout()
+            .with_brick_size(BrickSize::new([3, 5, 1, 1, 1, 1]));
+        layout.data_type = DataType::F64;
+        let metadata = VdsMetadata::new(layout).with_compression(CompressionMethod::Deflate);
+
+        let progress = Arc::new(AtomicUsize::new(0));
+        let reported = progress.clone();
+        let output = VolumeProcessor::new(&vds)
+            .with_halo([1, 1, 0, 0, 0, 0])
+            .with_max_concurrency(2)
+            .with_progress(move |p| {
+                reported.fetch_max(p.completed, Ordering::Relaxed);
+                assert_eq!(p.total, 6);
+            })
+            .map(
+                dir.path().join("smoothed").to_str().unwrap(),
+                metadata,
+                |brick| {
+                    // Sum over the 3x3 neighbourhood, clipped at the volume edges
+                    let mut values = Vec::new();
+                    for il in brick.range[0].0..brick.range[0].1 {
+                        for s in brick.range[1].0..brick.range[1].1 {
+                            let mut sum = 0.0;
+                            for nil in il.saturating_sub(1)..=(il + 1).min(SIZE[0] - 1) {
+                                for ns in s.saturating_sub(1)..=(s + 1).min(SIZE[1] - 1) {
+                                    sum += brick.get(&[nil, ns]);
+                                }
+                            }
+                            values.push(sum);
+                        }
+                    }
+                    Ok(values)
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(progress.load(Ordering::Relaxed), 6);
+
+        let result = output.read_slice_f32(&[0, 0], &SIZE).await.unwrap();
+        for il in 0..SIZE[0] {
+            for s in 0..SIZE[1] {
+                let mut expected = 0.0;
+                for nil in il.saturating_sub(1)..=(il + 1).min(SIZE[0] - 1) {
+                    for ns in s.saturating_sub(1)..=(s + 1).min(SIZE[1] - 1) {
+                        expected += input[nil * SIZE[1] + ns];
+                    }
+                }
+                assert_eq!(result[il * SIZE[1] + s], expected);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reduce() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+        let input = vds.read_slice_f32(&[0, 0], &SIZE).await.unwrap();
+
+        let processor = VolumeProcessor::new(&vds).with_halo([2, 2, 0, 0, 0, 0]);
+        let sum = processor
+            .reduce(
+                0.0,
+                |brick| Ok(brick.brick_samples().iter().sum::<f32>()),
+                |a, b| a + b,
+            )
+            .await
+            .unwrap();
+        assert_eq!(sum, input.iter().sum::<f32>());
+
+        let failed = processor
+            .map(
+                dir.path().join("bad").to_str().unwrap(),
+                VdsMetadata::new(vds.layout()),
+                |_| Ok(vec![0.0]),
+            )
+            .await;
+        assert!(matches!(failed, Err(VdsError::InvalidDimensions(_))));
+    }
+}
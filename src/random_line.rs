@@ -0,0 +1,444 @@
+//! Arbitrary polyline (random line) extraction
+//!
+//! A random line is a vertical section along a map polyline, e.g. a well-to-well correlation
+//! line. Traces are placed at a fixed spacing along the path, only the brick columns the path
+//! touches are read, and each trace is interpolated from the surrounding volume traces.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::layout::VolumeDataLayout;
+use futures::future::try_join_all;
+use ndarray::Array2;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Volume traces, by (inline, crossline) index, and weights that make up one output trace
+type Stencil = Vec<((usize, usize), f32)>;
+
+/// Coordinate system of polyline vertices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolylineCoordinates {
+    /// `[inline, crossline]` annotation values; trace spacing is in annotation units
+    Annotation,
+    /// World `[x, y]` positions through the survey transform; trace spacing is in world units
+    World,
+}
+
+/// Lateral interpolation of random line traces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceInterpolation {
+    /// Take the nearest volume trace
+    Nearest,
+    /// Blend the four surrounding volume traces
+    Bilinear,
+}
+
+/// A vertical section extracted along a polyline
+#[derive(Debug, Clone)]
+pub struct RandomLine {
+    /// Trace samples, indexed `[trace, sample]`
+    pub data: Array2<f32>,
+    /// Fractional `[inline, crossline]` annotation of each trace
+    pub annotations: Vec<[f64; 2]>,
+    /// World XY of each trace, if the survey has a transform
+    pub world_positions: Option<Vec<[f64; 2]>>,
+    /// Distance of each trace along the polyline, in the vertex coordinate units
+    pub distances: Vec<f64>,
+    /// Whether each trace lies inside the survey; traces outside are zero-filled
+    pub live: Vec<bool>,
+    /// Annotation coordinate of each sample
+    pub sample_coords: Vec<f64>,
+}
+
+impl VolumeDataAccess {
+    /// Extract a random line along `vertices`, placing a trace every `trace_spacing` units
+    ///
+    /// Traces start at the first vertex and continue while they fit on the path.
+    pub async fn read_random_line(
+        &self,
+        vertices: &[[f64; 2]],
+        coordinates: PolylineCoordinates,
+        trace_spacing: f64,
+        interpolation: TraceInterpolation,
+    ) -> Result<RandomLine> {
+        let metadata = self.metadata();
+        let layout = &metadata.layout;
+        let axes = layout.seismic_axes()?;
+        let transform = metadata.survey_metadata.as_ref().and_then(|s| s.transform);
+
+        if vertices.is_empty() {
+            return Err(VdsError::InvalidDimensions(
+                "Polyline needs at least one vertex".to_string(),
+            ));
+        }
+        if trace_spacing.is_nan() || trace_spacing <= 0.0 {
+            return Err(VdsError::Configuration(format!(
+                "Trace spacing must be positive, got {}",
+                trace_spacing
+            )));
+        }
+        for (dim, axis) in layout.axes.iter().enumerate() {
+            if dim != axes.inline
+                && dim != axes.crossline
+                && dim != axes.sample
+                && axis.num_samples != 1
+            {
+                return Err(VdsError::InvalidDimensions(format!(
+                    "Cannot extract a random line from a volume with {} samples on axis {}",
+                    axis.num_samples, axis.name
+                )));
+            }
+        }
+
+        // Place traces along the path in the vertex coordinate system
+        let (points, distances) = sample_polyline(vertices, trace_spacing);
+
+        let inline_axis = &layout.axes[axes.inline];
+        let crossline_axis = &layout.axes[axes.crossline];
+        let indices: Vec<[f64; 2]> = match coordinates {
+            PolylineCoordinates::Annotation => points
+                .iter()
+                .map(|p| {
+                    [
+                        inline_axis.coord_to_fractional_index(p[0]),
+                        crossline_axis.coord_to_fractional_index(p[1]),
+                    ]
+                })
+                .collect(),
+            PolylineCoordinates::World => {
+                let transform = transform.ok_or_else(|| {
+                    VdsError::MissingField("survey transform for world coordinates".to_string())
+                })?;
+                points
+                    .iter()
+                    .map(|p| {
+                        transform.world_to_index(p[0], p[1]).ok_or_else(|| {
+                            VdsError::Metadata("Survey transform is singular".to_string())
+                        })
+                    })
+                    .collect::<Result<_>>()?
+            }
+        };
+
+        // Volume traces each output trace is built from, with their weights
+        let (inlines, crosslines) = (inline_axis.num_samples, crossline_axis.num_samples);
+        let stencils: Vec<Option<Stencil>> = indices
+            .iter()
+            .map(|&[il, xl]| stencil(il, xl, inlines, crosslines, interpolation))
+            .collect();
+
+        let needed: Vec<(usize, usize)> = stencils
+            .iter()
+            .flatten()
+            .flat_map(|s| s.iter().map(|(node, _)| *node))
+            .collect();
+        let traces = self.read_traces(layout, &needed).await?;
+
+        let sample_axis = &layout.axes[axes.sample];
+        let num_samples = sample_axis.num_samples;
+        let mut data = Array2::<f32>::zeros((stencils.len(), num_samples));
+        for (row, stencil) in stencils.iter().enumerate() {
+            if let Some(stencil) = stencil {
+                let mut trace = data.row_mut(row);
+                for (node, weight) in stencil {
+                    for (out, &value) in trace.iter_mut().zip(traces[node].iter()) {
+                        *out += weight * value;
+                    }
+                }
+            }
+        }
+
+        let annotations = indices
+            .iter()
+            .map(|&[il, xl]| {
+                [
+                    inline_axis.coord_min + il * inline_axis.step_size(),
+                    crossline_axis.coord_min + xl * crossline_axis.step_size(),
+                ]
+            })
+            .collect();
+        let world_positions = transform.map(|t| {
+            indices
+                .iter()
+                .map(|&[il, xl]| t.index_to_world(il, xl))
+                .collect()
+        });
+
+        Ok(RandomLine {
+            data,
+            annotations,
+            world_positions,
+            distances,
+            live: stencils.iter().map(Option::is_some).collect(),
+            sample_coords: (0..num_samples)
+                .map(|i| sample_axis.index_to_coord(i))
+                .collect(),
+        })
+    }
+
+    /// Read full traces at the given (inline, crossline) indices, one read per brick column
+    async fn read_traces(
+        &self,
+        layout: &VolumeDataLayout,
+        nodes: &[(usize, usize)],
+    ) -> Result<HashMap<(usize, usize), Vec<f32>>> {
+        let axes = layout.seismic_axes()?;
+        let inline_brick = layout.brick_size.get(axes.inline);
+        let crossline_brick = layout.brick_size.get(axes.crossline);
+
+        // Bounding box of the needed traces within each brick column
+        let mut columns: BTreeMap<(usize, usize), [usize; 4]> = BTreeMap::new();
+        for &(il, xl) in nodes {
+            columns
+                .entry((il / inline_brick, xl / crossline_brick))
+                .and_modify(|b| {
+                    b[0] = b[0].min(il);
+                    b[1] = b[1].max(il + 1);
+                    b[2] = b[2].min(xl);
+                    b[3] = b[3].max(xl + 1);
+                })
+                .or_insert([il, il + 1, xl, xl + 1]);
+        }
+
+        let futures = columns.values().map(|&[il_min, il_max, xl_min, xl_max]| {
+            let mut min_coords = vec![0; layout.dimensionality];
+            let mut max_coords = layout.size();
+            min_coords[axes.inline] = il_min;
+            max_coords[axes.inline] = il_max;
+            min_coords[axes.crossline] = xl_min;
+            max_coords[axes.crossline] = xl_max;
+            async move {
+                let samples = self.read_slice_f32(&min_coords, &max_coords).await?;
+                Ok::<_, VdsError>((min_coords, max_coords, samples))
+            }
+        });
+
+        let mut traces = HashMap::new();
+        let wanted: HashSet<_> = nodes.iter().copied().collect();
+        for (min_coords, max_coords, samples) in try_join_all(futures).await? {
+            let dims: Vec<usize> = min_coords
+                .iter()
+                .zip(max_coords.iter())
+                .map(|(min, max)| max - min)
+                .collect();
+            let mut strides = vec![1usize; dims.len()];
+            for dim in (0..dims.len().saturating_sub(1)).rev() {
+                strides[dim] = strides[dim + 1] * dims[dim + 1];
+            }
+
+            for il in min_coords[axes.inline]..max_coords[axes.inline] {
+                for xl in min_coords[axes.crossline]..max_coords[axes.crossline] {
+                    if !wanted.contains(&(il, xl)) {
+                        continue;
+                    }
+                    let base = (il - min_coords[axes.inline]) * strides[axes.inline]
+                        + (xl - min_coords[axes.crossline]) * strides[axes.crossline];
+                    let trace = (0..dims[axes.sample])
+                        .map(|k| samples[base + k * strides[axes.sample]])
+                        .collect();
+                    traces.insert((il, xl), trace);
+                }
+            }
+        }
+
+        Ok(traces)
+    }
+}
+
+/// Points every `spacing` along a polyline and their distances from the first vertex
+fn sample_polyline(vertices: &[[f64; 2]], spacing: f64) -> (Vec<[f64; 2]>, Vec<f64>) {
+    let mut points = vec![vertices[0]];
+    let mut distances = vec![0.0];
+    let mut travelled = 0.0;
+    let mut next = spacing;
+
+    for segment in vertices.windows(2) {
+        let [a, b] = [segment[0], segment[1]];
+        let length = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+        // Tolerate rounding so a trace lands on a vertex that is a whole number of spacings away
+        while next <= travelled + length + spacing * 1e-9 {
+            let t = if length > 0.0 {
+                ((next - travelled) / length).min(1.0)
+            } else {
+                0.0
+            };
+            points.push([a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])]);
+            distances.push(next);
+            next += spacing;
+        }
+        travelled += length;
+    }
+
+    (points, distances)
+}
+
+/// Volume traces and weights for an output trace at fractional index (il, xl), or `None`
+/// outside the survey
+fn stencil(
+    il: f64,
+    xl: f64,
+    inlines: usize,
+    crosslines: usize,
+    interpolation: TraceInterpolation,
+) -> Option<Stencil> {
+    let inside = |v: f64, n: usize| (-0.5..=n as f64 - 0.5).contains(&v);
+    if !inside(il, inlines) || !inside(xl, crosslines) {
+        return None;
+    }
+
+    match interpolation {
+        TraceInterpolation::Nearest => {
+            let il = (il.round().max(0.0) as usize).min(inlines - 1);
+            let xl = (xl.round().max(0.0) as usize).min(crosslines - 1);
+            Some(vec![((il, xl), 1.0)])
+        }
+        TraceInterpolation::Bilinear => {
+            let il = il.clamp(0.0, (inlines - 1) as f64);
+            let xl = xl.clamp(0.0, (crosslines - 1) as f64);
+            let (il0, xl0) = (il.floor() as usize, xl.floor() as usize);
+            let (il1, xl1) = ((il0 + 1).min(inlines - 1), (xl0 + 1).min(crosslines - 1));
+            let (fi, fx) = ((il - il0 as f64) as f32, (xl - xl0 as f64) as f32);
+
+            let mut weights: Stencil = Vec::with_capacity(4);
+            for (node, weight) in [
+                ((il0, xl0), (1.0 - fi) * (1.0 - fx)),
+                ((il1, xl0), fi * (1.0 - fx)),
+                ((il0, xl1), (1.0 - fi) * fx),
+                ((il1, xl1), fi * fx),
+            ] {
+                if weight == 0.0 {
+                    continue;
+                }
+                match weights.iter_mut().find(|(n, _)| *n == node) {
+                    Some(entry) => entry.1 += weight,
+                    None => weights.push((node, weight)),
+                }
+            }
+            Some(weights)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::BrickSize;
+    use crate::metadata::{SurveyMetadata, SurveyTransform, VdsMetadata};
+    use crate::types::{AxisDescriptor, DataType};
+    use crate::utils::typed_data_to_bytes;
+    use tempfile::TempDir;
+
+    const INLINES: usize = 8;
+    const CROSSLINES: usize = 6;
+    const SAMPLES: usize = 3;
+
+    /// Value is linear in the indices: il * 10 + xl + s * 100
+    fn value(il: usize, xl: usize, s: usize) -> f32 {
+        (il * 10 + xl + s * 100) as f32
+    }
+
+    async fn create_test_volume(dir: &TempDir) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(INLINES, "Inline", "unitless", 100.0, 107.0),
+            AxisDescriptor::new(CROSSLINES, "Crossline", "unitless", 200.0, 210.0),
+            AxisDescriptor::new(SAMPLES, "Time", "ms", 0.0, 8.0),
+        ];
+        let layout = VolumeDataLayout::new(3, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([2, 2, 2, 1, 1, 1]));
+        let mut survey = SurveyMetadata::new("Test", "3D Seismic");
+        survey.transform = Some(SurveyTransform::new([0.0, 0.0], [0.0, 25.0], [25.0, 0.0]));
+        let metadata = VdsMetadata::new(layout).with_survey_metadata(survey);
+        VolumeDataAccess::create(dir.path().to_str().unwrap(), metadata)
+            .await
+            .unwrap()
+    }
+
+    async fn write_all(vds: &VolumeDataAccess) {
+        let mut values = Vec::new();
+        for il in 0..INLINES {
+            for xl in 0..CROSSLINES {
+                for s in 0..SAMPLES {
+                    values.push(value(il, xl, s));
+                }
+            }
+        }
+        vds.write_slice(
+            &[0, 0, 0],
+            &[INLINES, CROSSLINES, SAMPLES],
+            &typed_data_to_bytes(&values),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_sample_polyline() {
+        let (points, distances) = sample_polyline(&[[0.0, 0.0], [3.0, 0.0], [3.0, 2.0]], 1.0);
+        assert_eq!(distances, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(points[3], [3.0, 0.0]);
+        assert_eq!(points[5], [3.0, 2.0]);
+    }
+
+    #[tokio::test]
+    async fn test_random_line_annotation_bilinear() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+        write_all(&vds).await;
+
+        // Diagonal from (inline 101, crossline 202) to (inline 104, crossline 208)
+        let line = vds
+            .read_random_line(
+                &[[101.0, 202.0], [104.0, 208.0]],
+                PolylineCoordinates::Annotation,
+                45f64.sqrt() / 4.0,
+                TraceInterpolation::Bilinear,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(line.data.dim(), (5, SAMPLES));
+        assert!(line.live.iter().all(|&live| live));
+        // Second trace is at index (1.75, 1.75): linear data interpolates exactly
+        let expected = 1.75 * 10.0 + 1.75 + 100.0;
+        assert!((line.data[[1, 1]] - expected).abs() < 1e-4);
+        assert_eq!(line.data[[4, 2]], value(4, 4, 2));
+        assert_eq!(line.world_positions.as_ref().unwrap()[4], [100.0, 100.0]);
+    }
+
+    #[tokio::test]
+    async fn test_random_line_world_nearest_reads_only_touched_bricks() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+
+        // Only populate the brick column holding inlines 0-1, crosslines 2-3
+        let mut values = Vec::new();
+        for il in 0..2 {
+            for xl in 2..4 {
+                for s in 0..SAMPLES {
+                    values.push(value(il, xl, s));
+                }
+            }
+        }
+        vds.write_slice(&[0, 2, 0], &[2, 4, SAMPLES], &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+
+        // World x follows crosslines, y follows inlines; the last point is off the survey
+        let line = vds
+            .read_random_line(
+                &[[55.0, 10.0], [55.0, -40.0]],
+                PolylineCoordinates::World,
+                20.0,
+                TraceInterpolation::Nearest,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(line.live, vec![true, true, false]);
+        assert_eq!(line.data[[0, 0]], value(0, 2, 0));
+        assert_eq!(line.data[[1, 2]], value(0, 2, 2));
+        assert_eq!(line.data[[2, 1]], 0.0);
+        let [inline, crossline] = line.annotations[0];
+        assert!((inline - 100.4).abs() < 1e-9 && (crossline - 204.4).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,366 @@
+//! Resampling volumes onto new axis sampling
+//!
+//! The sample (time/depth) axis is resampled with band-limited sinc interpolation, low-pass
+//! filtered when the new interval is coarser. Other axes use nearest or linear interpolation.
+//! Each output brick is computed from the input box it depends on, one axis at a time.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::interpolation::{interpolate, lowpass_sinc, SampleInterpolation, SINC_HALF_WIDTH};
+use crate::types::AxisDescriptor;
+use crate::utils::f32_to_bytes;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use ndarray::{ArrayD, Axis, IxDyn};
+
+/// Number of output bricks computed concurrently
+const MAX_CONCURRENT_BRICKS: usize = 8;
+
+/// Interpolation across traces (every axis but the sample axis)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LateralInterpolation {
+    /// Nearest input position
+    Nearest,
+    /// Linear between the two surrounding positions
+    Linear,
+}
+
+/// How one output axis maps onto the input axis
+struct AxisMapping {
+    /// Fractional input index of each output sample
+    positions: Vec<f64>,
+    /// Output samples coincide with input samples
+    identity: bool,
+    /// Sinc cutoff for the sample axis, `None` for lateral axes
+    cutoff: Option<f64>,
+}
+
+impl AxisMapping {
+    /// Input index range `[min, max)` needed for output samples `[start, end)`
+    fn input_range(&self, start: usize, end: usize, num_samples: usize) -> (usize, usize) {
+        if self.identity {
+            return (start, end);
+        }
+        let (first, last) = (self.positions[start], self.positions[end - 1]);
+        let (low, high) = (
+            first.min(last).floor() as isize,
+            first.max(last).floor() as isize,
+        );
+        let support = match self.cutoff {
+            Some(cutoff) => (SINC_HALF_WIDTH as f64 / cutoff).ceil() as isize,
+            None => 1,
+        };
+        let clamp = |i: isize| i.clamp(0, num_samples as isize - 1) as usize;
+        let low = if self.cutoff.is_some() {
+            low - support + 1
+        } else {
+            low
+        };
+        (clamp(low), clamp(high + support) + 1)
+    }
+}
+
+impl VolumeDataAccess {
+    /// Resample the volume onto `axes` and write it to a new volume at `output_url`
+    ///
+    /// `axes` gives the new sampling of each dimension, in order, and must lie within the
+    /// input coordinate ranges; see [`AxisDescriptor::with_step`]. The output keeps the data
+    /// type, brick size, codec and survey metadata, with the survey transform rescaled to the
+    /// new inline/crossline sampling.
+    pub async fn resample(
+        &self,
+        axes: Vec<AxisDescriptor>,
+        lateral: LateralInterpolation,
+        output_url: impl Into<String>,
+    ) -> Result<VolumeDataAccess> {
+        let source = self.metadata();
+        let layout = &source.layout;
+        if axes.len() != layout.dimensionality {
+            return Err(VdsError::InvalidDimensions(format!(
+                "Expected {} axes, got {}",
+                layout.dimensionality,
+                axes.len()
+            )));
+        }
+        let sample_dim = layout.sample_axis_index().ok();
+
+        let mut mappings = Vec::with_capacity(axes.len());
+        for (dim, (input, output)) in layout.axes.iter().zip(&axes).enumerate() {
+            if output.num_samples == 0 {
+                return Err(VdsError::InvalidDimensions(format!(
+                    "Axis {} has no samples",
+                    output.name
+                )));
+            }
+            let positions: Vec<f64> = (0..output.num_samples)
+                .map(|i| input.coord_to_fractional_index(output.index_to_coord(i)))
+                .collect();
+            let limit = input.num_samples as f64 - 0.5;
+            if positions.iter().any(|p| !(-0.5..=limit).contains(p)) {
+                return Err(VdsError::OutOfBounds(format!(
+                    "Axis {} range {} - {} exceeds the input range {} - {}",
+                    output.name,
+                    output.coord_min,
+                    output.coord_max,
+                    input.coord_min,
+                    input.coord_max
+                )));
+            }
+
+            let identity = output.num_samples == input.num_samples
+                && positions
+                    .iter()
+                    .enumerate()
+                    .all(|(i, &p)| (p - i as f64).abs() < 1e-9);
+            let cutoff = (Some(dim) == sample_dim).then(|| {
+                let (input_step, output_step) = (input.step_size().abs(), output.step_size().abs());
+                if output_step > input_step && input_step > 0.0 {
+                    input_step / output_step
+                } else {
+                    1.0
+                }
+            });
+            mappings.push(AxisMapping {
+                positions,
+                identity,
+                cutoff,
+            });
+        }
+
+        let mut metadata = source.clone();
+        metadata.layout.axes = axes;
+        metadata.layout.lod_levels = 1;
+        metadata.statistics = None;
+        metadata.histograms.clear();
+        metadata.touch();
+        metadata.created_at = metadata.modified_at;
+        if let (Ok(seismic), Some(survey)) =
+            (layout.seismic_axes(), metadata.survey_metadata.as_mut())
+        {
+            if let Some(transform) = survey.transform.as_mut() {
+                let (il, xl) = (&mappings[seismic.inline], &mappings[seismic.crossline]);
+                let step = |m: &AxisMapping| m.positions.get(1).map_or(1.0, |p| p - m.positions[0]);
+                let (il_step, xl_step) = (step(il), step(xl));
+                transform.origin = transform.index_to_world(il.positions[0], xl.positions[0]);
+                transform.inline_spacing = transform.inline_spacing.map(|v| v * il_step);
+                transform.crossline_spacing = transform.crossline_spacing.map(|v| v * xl_step);
+            }
+        }
+
+        let output = self.create_derived(output_url, metadata).await?;
+        let target = output.layout();
+        let input_size = layout.size();
+        let method = match lateral {
+            LateralInterpolation::Nearest => SampleInterpolation::Nearest,
+            LateralInterpolation::Linear => SampleInterpolation::Linear,
+        };
+
+        stream::iter(0..target.total_bricks())
+            .map(|index| {
+                let (target, output, mappings, input_size) =
+                    (&target, &output, &mappings, &input_size);
+                async move {
+                    let range = target.brick_data_range(&target.brick_index_to_coords(index));
+                    let input_range: Vec<(usize, usize)> = range
+                        .iter()
+                        .zip(mappings)
+                        .zip(input_size)
+                        .map(|((&(start, end), mapping), &n)| mapping.input_range(start, end, n))
+                        .collect();
+                    let min_coords: Vec<usize> = input_range.iter().map(|r| r.0).collect();
+                    let max_coords: Vec<usize> = input_range.iter().map(|r| r.1).collect();
+                    let samples = self.read_slice_f32(&min_coords, &max_coords).await?;
+                    let shape: Vec<usize> = input_range.iter().map(|(a, b)| b - a).collect();
+                    let mut data = ArrayD::from_shape_vec(IxDyn(&shape), samples)
+                        .map_err(|e| VdsError::InvalidDimensions(e.to_string()))?;
+
+                    for (dim, mapping) in mappings.iter().enumerate() {
+                        if mapping.identity {
+                            continue;
+                        }
+                        let (start, end) = range[dim];
+                        let offset = input_range[dim].0 as f64;
+                        let mut shape = data.shape().to_vec();
+                        shape[dim] = end - start;
+                        let mut resampled = ArrayD::zeros(IxDyn(&shape));
+                        let mut trace = Vec::with_capacity(data.shape()[dim]);
+                        for (lane, mut out) in data
+                            .lanes(Axis(dim))
+                            .into_iter()
+                            .zip(resampled.lanes_mut(Axis(dim)))
+                        {
+                            trace.clear();
+                            trace.extend(lane.iter().copied());
+                            for (j, value) in out.iter_mut().enumerate() {
+                                let position = mapping.positions[start + j] - offset;
+                                *value = match mapping.cutoff {
+                                    Some(cutoff) => lowpass_sinc(&trace, position, cutoff),
+                                    None => interpolate(&trace, position, method),
+                                };
+                            }
+                        }
+                        data = resampled;
+                    }
+
+                    let values: Vec<f32> = data.iter().copied().collect();
+                    output
+                        .write_brick(index, &f32_to_bytes(&values, target.data_type))
+                        .await
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_BRICKS)
+            .try_collect::<Vec<()>>()
+            .await?;
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::metadata::{SurveyMetadata, SurveyTransform, VdsMetadata};
+    use crate::types::DataType;
+    use crate::utils::typed_data_to_bytes;
+    use std::f64::consts::PI;
+    use tempfile::TempDir;
+
+    const INLINES: usize = 6;
+    const SAMPLES: usize = 101;
+
+    /// 10 Hz sine sampled every 4 ms, amplitude growing with the inline
+    fn value(il: f64, time_ms: f64) -> f64 {
+        (il + 1.0) * (2.0 * PI * 10.0 * time_ms / 1000.0).sin()
+    }
+
+    async fn create_test_volume(dir: &TempDir) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(INLINES, "Inline", "unitless", 1000.0, 1005.0),
+            AxisDescriptor::new(3, "Crossline", "unitless", 1.0, 3.0),
+            AxisDescriptor::new(SAMPLES, "Time", "ms", 0.0, 400.0),
+        ];
+        let layout = VolumeDataLayout::new(3, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 4, 32, 1, 1, 1]));
+        let mut survey = SurveyMetadata::new("Resample", "3D Seismic");
+        survey.transform = Some(SurveyTransform::new([0.0, 0.0], [25.0, 0.0], [0.0, 12.5]));
+        let metadata = VdsMetadata::new(layout).with_survey_metadata(survey);
+        let path = dir.path().join("input");
+        let vds = VolumeDataAccess::create(path.to_str().unwrap(), metadata)
+            .await
+            .unwrap();
+
+        let mut values = Vec::new();
+        for il in 0..INLINES {
+            for _ in 0..3 {
+                for s in 0..SAMPLES {
+                    values.push(value(il as f64, s as f64 * 4.0) as f32);
+                }
+            }
+        }
+        vds.write_slice(
+            &[0, 0, 0],
+            &[INLINES, 3, SAMPLES],
+            &typed_data_to_bytes(&values),
+        )
+        .await
+        .unwrap();
+        vds
+    }
+
+    #[tokio::test]
+    async fn test_resample_time_to_2ms() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+        let mut axes = vds.layout().axes;
+        axes[2] = axes[2].with_step(2.0);
+
+        let path = dir.path().join("2ms");
+        let output = vds
+            .resample(axes, LateralInterpolation::Linear, path.to_str().unwrap())
+            .await
+            .unwrap();
+        let layout = output.layout();
+        assert_eq!(layout.axes[2].num_samples, 201);
+        assert_eq!(layout.axes[2].step_size(), 2.0);
+
+        let data = output
+            .read_slice_f32(&[3, 1, 0], &[4, 2, 201])
+            .await
+            .unwrap();
+        for (s, &v) in data.iter().enumerate() {
+            let expected = value(3.0, s as f64 * 2.0);
+            // Away from the trace ends the sinc reconstruction is close to the true signal
+            if (20..180).contains(&s) {
+                assert!((v as f64 - expected).abs() < 0.02, "sample {}", s);
+            }
+            if s % 2 == 0 {
+                assert_eq!(v as f64, value(3.0, s as f64 * 2.0) as f32 as f64);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decimate_inlines() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+        let input = vds
+            .read_slice_f32(&[0, 0, 0], &[INLINES, 1, SAMPLES])
+            .await
+            .unwrap();
+
+        // Every second inline
+        let mut axes = vds.layout().axes;
+        axes[0] = axes[0].with_step(2.0);
+        let path = dir.path().join("decimated");
+        let output = vds
+            .resample(
+                axes.clone(),
+                LateralInterpolation::Nearest,
+                path.to_str().unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.layout().axes[0].num_samples, 3);
+        assert_eq!(output.layout().axes[0].coord_max, 1004.0);
+        let data = output
+            .read_slice_f32(&[2, 0, 0], &[3, 1, SAMPLES])
+            .await
+            .unwrap();
+        assert_eq!(data, input[4 * SAMPLES..5 * SAMPLES]);
+
+        let transform = output
+            .metadata()
+            .survey_metadata
+            .unwrap()
+            .transform
+            .unwrap();
+        assert_eq!(transform.inline_spacing, [50.0, 0.0]);
+        assert_eq!(transform.index_to_world(2.0, 0.0), [100.0, 0.0]);
+
+        // Half-way inlines with linear interpolation
+        axes[0] = AxisDescriptor::new(2, "Inline", "unitless", 1000.5, 1001.5);
+        let path = dir.path().join("shifted");
+        let output = vds
+            .resample(
+                axes.clone(),
+                LateralInterpolation::Linear,
+                path.to_str().unwrap(),
+            )
+            .await
+            .unwrap();
+        let data = output
+            .read_slice_f32(&[1, 0, 10], &[2, 1, 11])
+            .await
+            .unwrap();
+        let expected = (input[SAMPLES + 10] + input[2 * SAMPLES + 10]) / 2.0;
+        assert!((data[0] - expected).abs() < 1e-6);
+
+        axes[0] = AxisDescriptor::new(2, "Inline", "unitless", 1000.0, 1010.0);
+        let path = dir.path().join("outside");
+        assert!(vds
+            .resample(axes, LateralInterpolation::Linear, path.to_str().unwrap())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! Inline, crossline and sample-slice reads for seismic volumes
+//!
+//! Sections are addressed by annotation value (e.g. inline 10200 or 1500 ms), resolved through
+//! the `Inline`, `Crossline` and sample axis descriptors, and returned as 2D arrays.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::types::AxisDescriptor;
+use ndarray::{Array2, ArrayD, Axis, Ix2, IxDyn};
+
+/// A 2D section of a volume with the annotation coordinates of its rows and columns
+#[derive(Debug, Clone)]
+pub struct Section {
+    /// Section samples, indexed `[row, column]`
+    pub data: Array2<f32>,
+    /// Name of the axis along the rows
+    pub row_axis: String,
+    /// Name of the axis along the columns
+    pub column_axis: String,
+    /// Annotation coordinate of each row
+    pub row_coords: Vec<f64>,
+    /// Annotation coordinate of each column
+    pub column_coords: Vec<f64>,
+}
+
+impl Section {
+    /// Number of rows and columns
+    pub fn shape(&self) -> (usize, usize) {
+        self.data.dim()
+    }
+}
+
+impl VolumeDataAccess {
+    /// Read an inline section: rows are crosslines, columns are samples
+    pub async fn read_inline(&self, inline: f64) -> Result<Section> {
+        let axes = self.layout().seismic_axes()?;
+        self.read_section(axes.inline, inline, axes.crossline, axes.sample)
+            .await
+    }
+
+    /// Read a crossline section: rows are inlines, columns are samples
+    pub async fn read_crossline(&self, crossline: f64) -> Result<Section> {
+        let axes = self.layout().seismic_axes()?;
+        self.read_section(axes.crossline, crossline, axes.inline, axes.sample)
+            .await
+    }
+
+    /// Read a time/depth slice: rows are inlines, columns are crosslines
+    pub async fn read_sample_slice(&self, sample: f64) -> Result<Section> {
+        let axes = self.layout().seismic_axes()?;
+        self.read_section(axes.sample, sample, axes.inline, axes.crossline)
+            .await
+    }
+
+    /// Read the plane at annotation `coord` of `fixed_dim`, spanning `row_dim` x `column_dim`
+    ///
+    /// `coord` must fall on a sample of the axis; coordinates between samples are rejected
+    /// rather than snapped. Only the bricks intersecting the plane are read. Any other dimension
+    /// must have a single sample.
+    pub async fn read_section(
+        &self,
+        fixed_dim: usize,
+        coord: f64,
+        row_dim: usize,
+        column_dim: usize,
+    ) -> Result<Section> {
+        let layout = self.layout();
+        let fixed_axis = &layout.axes[fixed_dim];
+        if fixed_axis.try_coord_to_index(coord).is_none() {
+            return Err(VdsError::OutOfBounds(format!(
+                "{} {} outside axis range {} - {}",
+                fixed_axis.name, coord, fixed_axis.coord_min, fixed_axis.coord_max
+            )));
+        }
+        let index = fixed_axis.try_sample_index(coord).ok_or_else(|| {
+            VdsError::OutOfBounds(format!(
+                "{} {} is not on an axis sample (step {} from {})",
+                fixed_axis.name,
+                coord,
+                fixed_axis.step_size(),
+                fixed_axis.coord_min
+            ))
+        })?;
+
+        for (dim, axis) in layout.axes.iter().enumerate() {
+            if dim != fixed_dim && dim != row_dim && dim != column_dim && axis.num_samples != 1 {
+                return Err(VdsError::InvalidDimensions(format!(
+                    "Cannot read a 2D section from a volume with {} samples on axis {}",
+                    axis.num_samples, axis.name
+                )));
+            }
+        }
+
+        let mut min_coords = vec![0; layout.dimensionality];
+        let mut max_coords = layout.size();
+        min_coords[fixed_dim] = index;
+        max_coords[fixed_dim] = index + 1;
+
+        let samples = self.read_slice_f32(&min_coords, &max_coords).await?;
+        let shape: Vec<usize> = min_coords
+            .iter()
+            .zip(max_coords.iter())
+            .map(|(min, max)| max - min)
+            .collect();
+        let volume = ArrayD::from_shape_vec(IxDyn(&shape), samples)
+            .map_err(|e| VdsError::InvalidDimensions(e.to_string()))?;
+
+        // Move the row and column dimensions to the end and drop the single-sample ones
+        let mut order: Vec<usize> = (0..layout.dimensionality)
+            .filter(|&dim| dim != row_dim && dim != column_dim)
+            .collect();
+        order.push(row_dim);
+        order.push(column_dim);
+        let mut plane = volume.permuted_axes(IxDyn(&order));
+        while plane.ndim() > 2 {
+            plane = plane.index_axis_move(Axis(0), 0);
+        }
+        let data = plane
+            .into_dimensionality::<Ix2>()
+            .map_err(|e| VdsError::InvalidDimensions(e.to_string()))?
+            .as_standard_layout()
+            .into_owned();
+
+        let coords = |axis: &AxisDescriptor| {
+            (0..axis.num_samples)
+                .map(|i| axis.index_to_coord(i))
+                .collect()
+        };
+
+        Ok(Section {
+            data,
+            row_axis: layout.axes[row_dim].name.clone(),
+            column_axis: layout.axes[column_dim].name.clone(),
+            row_coords: coords(&layout.axes[row_dim]),
+            column_coords: coords(&layout.axes[column_dim]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::metadata::VdsMetadata;
+    use crate::types::DataType;
+    use crate::utils::typed_data_to_bytes;
+    use tempfile::TempDir;
+
+    /// OSDU axis order: sample fastest, inline slowest
+    async fn create_test_volume(dir: &TempDir) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(5, "Sample", "ms", 0.0, 16.0),
+            AxisDescriptor::new(3, "Crossline", "unitless", 1932.0, 1934.0),
+            AxisDescriptor::new(4, "Inline", "unitless", 10200.0, 10206.0),
+        ];
+        let layout = VolumeDataLayout::new(3, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([2, 2, 2, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(dir.path().to_str().unwrap(), VdsMetadata::new(layout))
+            .await
+            .unwrap();
+
+        // Value encodes (inline, crossline, sample) indices as il*100 + xl*10 + s; the buffer
+        // is row-major over (sample, crossline, inline)
+        let mut values = Vec::new();
+        for s in 0..5 {
+            for xl in 0..3 {
+                for il in 0..4 {
+                    values.push((il * 100 + xl * 10 + s) as f32);
+                }
+            }
+        }
+        vds.write_slice(&[0, 0, 0], &[5, 3, 4], &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+        vds
+    }
+
+    #[tokio::test]
+    async fn test_read_inline_and_crossline() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+
+        let section = vds.read_inline(10204.0).await.unwrap();
+        assert_eq!(section.shape(), (3, 5));
+        assert_eq!(section.row_axis, "Crossline");
+        assert_eq!(section.column_coords, vec![0.0, 4.0, 8.0, 12.0, 16.0]);
+        assert_eq!(section.data[[1, 3]], 213.0);
+
+        let section = vds.read_crossline(1934.0).await.unwrap();
+        assert_eq!(section.shape(), (4, 5));
+        assert_eq!(section.row_coords, vec![10200.0, 10202.0, 10204.0, 10206.0]);
+        assert_eq!(section.data[[3, 4]], 324.0);
+    }
+
+    #[tokio::test]
+    async fn test_read_sample_slice() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+
+        let section = vds.read_sample_slice(8.0).await.unwrap();
+        assert_eq!(section.shape(), (4, 3));
+        assert_eq!(section.column_axis, "Crossline");
+        assert_eq!(section.data[[2, 1]], 212.0);
+
+        assert!(matches!(
+            vds.read_inline(10300.0).await,
+            Err(VdsError::OutOfBounds(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_off_grid_section_rejected() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+
+        // Inlines are every 2 and samples every 4 ms
+        assert!(matches!(
+            vds.read_inline(10201.0).await,
+            Err(VdsError::OutOfBounds(_))
+        ));
+        assert!(vds.read_sample_slice(7.0).await.is_err());
+        assert!(vds.read_inline(10202.0 + 1e-9).await.is_ok());
+    }
+}
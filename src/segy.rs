@@ -0,0 +1,859 @@
+//! SEG-Y export for seismic volumes
+//!
+//! Volumes are written trace by trace in inline order. The textual and binary file headers are
+//! regenerated from [`SegyMetadata`], and trace headers are either restored from the headers
+//! stored alongside the volume or synthesised from the axis annotations and survey transform.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::ibm::ieee_to_ibm;
+use crate::layout::SeismicAxes;
+use crate::metadata::{SegyMetadata, SurveyTransform};
+use crate::types::{AxisDescriptor, DataType};
+use crate::utils::bytes_to_f32;
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Size of the textual file header in bytes
+pub const TEXT_HEADER_SIZE: usize = 3200;
+
+/// Size of the binary file header in bytes
+pub const BINARY_HEADER_SIZE: usize = 400;
+
+/// Size of a trace header in bytes
+pub const TRACE_HEADER_SIZE: usize = 240;
+
+/// Storage path of the original SEG-Y trace headers within a volume
+///
+/// The object holds one 240-byte header per trace, inline-major over the full inline/crossline grid.
+pub const TRACE_HEADERS_PATH: &str = "segy/TraceHeaders.bin";
+
+/// Standard binary header fields: name, byte offset within the binary header and width in bytes
+///
+/// Values in [`SegyMetadata::binary_header`] are written to the field with the matching name.
+pub const BINARY_HEADER_FIELDS: &[(&str, usize, usize)] = &[
+    ("JobId", 0, 4),
+    ("LineNumber", 4, 4),
+    ("ReelNumber", 8, 4),
+    ("DataTracesPerEnsemble", 12, 2),
+    ("AuxTracesPerEnsemble", 14, 2),
+    ("SampleInterval", 16, 2),
+    ("OriginalSampleInterval", 18, 2),
+    ("SamplesPerTrace", 20, 2),
+    ("OriginalSamplesPerTrace", 22, 2),
+    ("DataSampleFormatCode", 24, 2),
+    ("EnsembleFold", 26, 2),
+    ("TraceSortingCode", 28, 2),
+    ("VerticalSumCode", 30, 2),
+    ("SweepFrequencyStart", 32, 2),
+    ("SweepFrequencyEnd", 34, 2),
+    ("SweepLength", 36, 2),
+    ("SweepTypeCode", 38, 2),
+    ("SweepChannelTraceNumber", 40, 2),
+    ("SweepTaperLengthStart", 42, 2),
+    ("SweepTaperLengthEnd", 44, 2),
+    ("TaperType", 46, 2),
+    ("CorrelatedTraces", 48, 2),
+    ("BinaryGainRecovered", 50, 2),
+    ("AmplitudeRecoveryMethod", 52, 2),
+    ("MeasurementSystem", 54, 2),
+    ("ImpulseSignalPolarity", 56, 2),
+    ("VibratoryPolarityCode", 58, 2),
+    ("ExtendedDataTracesPerEnsemble", 60, 4),
+    ("ExtendedAuxTracesPerEnsemble", 64, 4),
+    ("ExtendedSamplesPerTrace", 68, 4),
+    ("ExtendedOriginalSamplesPerTrace", 88, 4),
+    ("ExtendedEnsembleFold", 92, 4),
+    ("IntegerConstant", 96, 4),
+    ("SegyFormatRevisionNumber", 300, 2),
+    ("FixedLengthTraceFlag", 302, 2),
+    ("ExtendedTextualHeaders", 304, 2),
+];
+
+/// Default 1-based trace header byte positions, overridable through
+/// [`SegyMetadata::trace_header_mappings`]
+const DEFAULT_TRACE_HEADER_POSITIONS: &[(&str, usize)] = &[
+    ("Inline", 189),
+    ("Crossline", 193),
+    ("CdpX", 181),
+    ("CdpY", 185),
+];
+
+/// Coordinate scalar written to synthesised headers (coordinates in centimetres)
+const COORDINATE_SCALAR: i16 = -100;
+
+/// Sample format of exported traces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegySampleFormat {
+    /// 4-byte IBM System/360 floating point (format code 1)
+    IbmFloat,
+    /// 4-byte IEEE floating point (format code 5)
+    IeeeFloat,
+}
+
+impl SegySampleFormat {
+    /// SEG-Y data sample format code
+    pub fn format_code(&self) -> i32 {
+        match self {
+            SegySampleFormat::IbmFloat => 1,
+            SegySampleFormat::IeeeFloat => 5,
+        }
+    }
+}
+
+/// Character encoding of the textual file header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextHeaderEncoding {
+    /// EBCDIC (code page 037), the SEG-Y rev 1 standard
+    Ebcdic,
+    /// Plain ASCII, allowed from SEG-Y rev 2
+    Ascii,
+}
+
+/// Summary of a completed export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegyExportSummary {
+    /// Number of traces written
+    pub traces: usize,
+    /// Number of samples per trace
+    pub samples_per_trace: usize,
+    /// Total size of the SEG-Y file in bytes
+    pub bytes_written: u64,
+}
+
+/// Exports a volume, or a sub-volume of it, to SEG-Y
+pub struct SegyExporter<'a> {
+    vds: &'a VolumeDataAccess,
+    revision: Option<u16>,
+    sample_format: SegySampleFormat,
+    text_encoding: TextHeaderEncoding,
+    subvolume: Option<(Vec<usize>, Vec<usize>)>,
+}
+
+impl<'a> SegyExporter<'a> {
+    /// Create an exporter writing IEEE floats with an EBCDIC textual header
+    pub fn new(vds: &'a VolumeDataAccess) -> Self {
+        Self {
+            vds,
+            revision: None,
+            sample_format: SegySampleFormat::IeeeFloat,
+            text_encoding: TextHeaderEncoding::Ebcdic,
+            subvolume: None,
+        }
+    }
+
+    /// Set the SEG-Y revision (1 or 2); defaults to the stored revision, or 1
+    pub fn with_revision(mut self, revision: u16) -> Self {
+        self.revision = Some(revision);
+        self
+    }
+
+    /// Set the trace sample format
+    pub fn with_sample_format(mut self, format: SegySampleFormat) -> Self {
+        self.sample_format = format;
+        self
+    }
+
+    /// Set the textual header encoding
+    pub fn with_text_encoding(mut self, encoding: TextHeaderEncoding) -> Self {
+        self.text_encoding = encoding;
+        self
+    }
+
+    /// Restrict the export to the voxel box `[min_coords, max_coords)`
+    pub fn with_subvolume(mut self, min_coords: &[usize], max_coords: &[usize]) -> Self {
+        self.subvolume = Some((min_coords.to_vec(), max_coords.to_vec()));
+        self
+    }
+
+    /// Export to a file on the local filesystem
+    pub async fn export_to_file(&self, path: impl AsRef<Path>) -> Result<SegyExportSummary> {
+        let file = tokio::fs::File::create(path.as_ref()).await?;
+        let mut writer = tokio::io::BufWriter::new(file);
+        let summary = self.export(&mut writer).await?;
+        writer.flush().await?;
+        Ok(summary)
+    }
+
+    /// Export to an async writer, streaming traces in inline order
+    pub async fn export<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> Result<SegyExportSummary> {
+        let metadata = self.vds.metadata();
+        let layout = &metadata.layout;
+        let axes = layout.seismic_axes()?;
+
+        let (min_coords, max_coords) = match &self.subvolume {
+            Some((min, max)) => (min.clone(), max.clone()),
+            None => (vec![0; layout.dimensionality], layout.size()),
+        };
+        self.validate_box(
+            layout.dimensionality,
+            &layout.size(),
+            &axes,
+            &min_coords,
+            &max_coords,
+        )?;
+
+        let survey = metadata.survey_metadata.as_ref();
+        let segy = survey.and_then(|s| s.segy_metadata.as_ref());
+        let transform = survey.and_then(|s| s.transform);
+        let revision = self.revision.or(segy.map(|s| s.revision)).unwrap_or(1);
+        if revision != 1 && revision != 2 {
+            return Err(VdsError::Configuration(format!(
+                "Unsupported SEG-Y revision {} (expected 1 or 2)",
+                revision
+            )));
+        }
+
+        let inline_axis = &layout.axes[axes.inline];
+        let crossline_axis = &layout.axes[axes.crossline];
+        let sample_axis = &layout.axes[axes.sample];
+        let num_samples = max_coords[axes.sample] - min_coords[axes.sample];
+        let sample_interval = sample_interval_micros(sample_axis);
+        if num_samples > u16::MAX as usize || sample_interval > u16::MAX as u32 {
+            return Err(VdsError::InvalidDimensions(format!(
+                "{} samples at {} us cannot be represented in SEG-Y trace headers",
+                num_samples, sample_interval
+            )));
+        }
+
+        let positions = trace_header_positions(segy)?;
+        let stored_headers = self.vds.read_segy_trace_headers().await?;
+        if let Some(headers) = &stored_headers {
+            let expected = inline_axis.num_samples * crossline_axis.num_samples * TRACE_HEADER_SIZE;
+            if headers.len() != expected {
+                return Err(VdsError::InvalidFormat(format!(
+                    "Stored trace headers have {} bytes, expected {}",
+                    headers.len(),
+                    expected
+                )));
+            }
+        }
+
+        // File headers
+        let text_header = self.text_header(
+            segy,
+            survey.map(|s| s.survey_name.as_str()),
+            &axes,
+            layout.axes.as_slice(),
+            (&min_coords, &max_coords),
+            revision,
+        );
+        let binary_header = self.binary_header(segy, num_samples, sample_interval, revision);
+        writer.write_all(&text_header).await?;
+        writer.write_all(&binary_header).await?;
+        let mut bytes_written = (TEXT_HEADER_SIZE + BINARY_HEADER_SIZE) as u64;
+
+        let trace_size = TRACE_HEADER_SIZE + num_samples * 4;
+        let inline_step = layout.brick_size.get(axes.inline);
+        let mut traces = 0usize;
+
+        // Read one brick row of inlines at a time and stream its traces out
+        let mut inline_start = min_coords[axes.inline];
+        while inline_start < max_coords[axes.inline] {
+            let inline_end =
+                ((inline_start / inline_step + 1) * inline_step).min(max_coords[axes.inline]);
+
+            let mut slab_min = min_coords.clone();
+            let mut slab_max = max_coords.clone();
+            slab_min[axes.inline] = inline_start;
+            slab_max[axes.inline] = inline_end;
+
+            let data = self.vds.read_slice(&slab_min, &slab_max).await?;
+            // IBM volumes exported as IBM are copied word for word, without a round trip
+            let passthrough = layout.data_type == DataType::IbmF32
+                && self.sample_format == SegySampleFormat::IbmFloat;
+            let samples = if passthrough {
+                Vec::new()
+            } else {
+                bytes_to_f32(&data, layout.data_type)?
+            };
+
+            let slab_dims: Vec<usize> = slab_min
+                .iter()
+                .zip(slab_max.iter())
+                .map(|(min, max)| max - min)
+                .collect();
+            let mut strides = vec![1usize; slab_dims.len()];
+            for dim in (0..slab_dims.len().saturating_sub(1)).rev() {
+                strides[dim] = strides[dim + 1] * slab_dims[dim + 1];
+            }
+
+            let crosslines = slab_dims[axes.crossline];
+            let mut buffer =
+                Vec::with_capacity((inline_end - inline_start) * crosslines * trace_size);
+
+            for inline_index in inline_start..inline_end {
+                for crossline_index in min_coords[axes.crossline]..max_coords[axes.crossline] {
+                    let mut header = match &stored_headers {
+                        Some(headers) => {
+                            let offset = (inline_index * crossline_axis.num_samples
+                                + crossline_index)
+                                * TRACE_HEADER_SIZE;
+                            let mut header = [0u8; TRACE_HEADER_SIZE];
+                            header.copy_from_slice(&headers[offset..offset + TRACE_HEADER_SIZE]);
+                            header
+                        }
+                        None => synthesise_trace_header(
+                            &positions,
+                            inline_axis,
+                            crossline_axis,
+                            transform.as_ref(),
+                            inline_index,
+                            crossline_index,
+                            traces,
+                        ),
+                    };
+
+                    put_i32(
+                        &mut header,
+                        1,
+                        (crossline_index - min_coords[axes.crossline] + 1) as i32,
+                    );
+                    put_i32(&mut header, 5, (traces + 1) as i32);
+                    put_i16(&mut header, 115, num_samples as i16);
+                    put_i16(&mut header, 117, sample_interval as i16);
+                    if stored_headers.is_none() || min_coords[axes.sample] > 0 {
+                        let delay = sample_axis.index_to_coord(min_coords[axes.sample]);
+                        put_i16(&mut header, 109, delay.round() as i16);
+                    }
+                    buffer.extend_from_slice(&header);
+
+                    let base = (inline_index - inline_start) * strides[axes.inline]
+                        + (crossline_index - min_coords[axes.crossline]) * strides[axes.crossline];
+                    for k in 0..num_samples {
+                        let index = base + k * strides[axes.sample];
+                        if passthrough {
+                            buffer.extend_from_slice(&data[index * 4..index * 4 + 4]);
+                            continue;
+                        }
+                        let word = match self.sample_format {
+                            SegySampleFormat::IeeeFloat => samples[index].to_bits(),
+                            SegySampleFormat::IbmFloat => ieee_to_ibm(samples[index]),
+                        };
+                        buffer.extend_from_slice(&word.to_be_bytes());
+                    }
+
+                    traces += 1;
+                }
+            }
+
+            writer.write_all(&buffer).await?;
+            bytes_written += buffer.len() as u64;
+            inline_start = inline_end;
+        }
+
+        Ok(SegyExportSummary {
+            traces,
+            samples_per_trace: num_samples,
+            bytes_written,
+        })
+    }
+
+    /// Check the export box against the volume, allowing only single samples on non-seismic axes
+    fn validate_box(
+        &self,
+        dimensionality: usize,
+        size: &[usize],
+        axes: &SeismicAxes,
+        min_coords: &[usize],
+        max_coords: &[usize],
+    ) -> Result<()> {
+        if min_coords.len() != dimensionality || max_coords.len() != dimensionality {
+            return Err(VdsError::InvalidDimensions(
+                "Coordinate dimensions don't match volume dimensionality".to_string(),
+            ));
+        }
+
+        for dim in 0..dimensionality {
+            if min_coords[dim] >= max_coords[dim] || max_coords[dim] > size[dim] {
+                return Err(VdsError::OutOfBounds(format!(
+                    "Invalid export range {}..{} on dimension {}",
+                    min_coords[dim], max_coords[dim], dim
+                )));
+            }
+            let seismic = dim == axes.inline || dim == axes.crossline || dim == axes.sample;
+            if !seismic && max_coords[dim] - min_coords[dim] != 1 {
+                return Err(VdsError::InvalidDimensions(format!(
+                    "Dimension {} must be restricted to a single sample for SEG-Y export",
+                    dim
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the 3200-byte textual header from stored lines, or describe the volume if none
+    fn text_header(
+        &self,
+        segy: Option<&SegyMetadata>,
+        survey_name: Option<&str>,
+        axes: &SeismicAxes,
+        descriptors: &[AxisDescriptor],
+        (min_coords, max_coords): (&[usize], &[usize]),
+        revision: u16,
+    ) -> Vec<u8> {
+        let mut lines: Vec<String> = segy
+            .map(|s| {
+                s.text_header
+                    .iter()
+                    .flat_map(|line| {
+                        let chars: Vec<char> = line.chars().collect();
+                        chars
+                            .chunks(80)
+                            .map(|chunk| chunk.iter().collect::<String>())
+                            .collect::<Vec<_>>()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if lines.is_empty() {
+            let range = |dim: usize| {
+                let axis = &descriptors[dim];
+                (
+                    axis.index_to_coord(min_coords[dim]),
+                    axis.index_to_coord(max_coords[dim] - 1),
+                )
+            };
+            let (il_min, il_max) = range(axes.inline);
+            let (xl_min, xl_max) = range(axes.crossline);
+            let (s_min, s_max) = range(axes.sample);
+            let sample_axis = &descriptors[axes.sample];
+
+            lines = (1..=40).map(|n| format!("C{:2}", n)).collect();
+            lines[0].push_str(&format!(" {}", survey_name.unwrap_or("OPENVDS EXPORT")));
+            lines[1].push_str(&format!(
+                " INLINES {} - {}, CROSSLINES {} - {}",
+                il_min, il_max, xl_min, xl_max
+            ));
+            lines[2].push_str(&format!(
+                " {} {} - {} {}, {} SAMPLES",
+                sample_axis.name.to_uppercase(),
+                s_min,
+                s_max,
+                sample_axis.unit,
+                max_coords[axes.sample] - min_coords[axes.sample]
+            ));
+            lines[3].push_str(match self.sample_format {
+                SegySampleFormat::IbmFloat => " DATA FORMAT: 4-BYTE IBM FLOAT",
+                SegySampleFormat::IeeeFloat => " DATA FORMAT: 4-BYTE IEEE FLOAT",
+            });
+            lines[4].push_str(&format!(
+                " EXPORTED BY OPENVDS-RS {}",
+                crate::OPENVDS_VERSION
+            ));
+            lines[38].push_str(&format!(" SEG-Y REV{}", revision));
+            lines[39].push_str(if revision >= 2 {
+                " END TEXTUAL HEADER"
+            } else {
+                " END EBCDIC"
+            });
+        }
+
+        let mut header = vec![b' '; TEXT_HEADER_SIZE];
+        for (row, line) in lines.iter().take(40).enumerate() {
+            for (col, c) in line.chars().take(80).enumerate() {
+                header[row * 80 + col] = if c.is_ascii() { c as u8 } else { b' ' };
+            }
+        }
+
+        if self.text_encoding == TextHeaderEncoding::Ebcdic {
+            for byte in header.iter_mut() {
+                *byte = ascii_to_ebcdic(*byte);
+            }
+        }
+
+        header
+    }
+
+    /// Build the 400-byte binary header, overriding the fields this export determines
+    fn binary_header(
+        &self,
+        segy: Option<&SegyMetadata>,
+        num_samples: usize,
+        sample_interval: u32,
+        revision: u16,
+    ) -> Vec<u8> {
+        let mut values: HashMap<&str, i32> = HashMap::new();
+        if let Some(segy) = segy {
+            for (name, _, _) in BINARY_HEADER_FIELDS {
+                if let Some(&value) = segy.binary_header.get(*name) {
+                    values.insert(name, value);
+                }
+            }
+        }
+
+        values.insert("SampleInterval", sample_interval as i32);
+        values
+            .entry("OriginalSampleInterval")
+            .or_insert(sample_interval as i32);
+        values.insert("SamplesPerTrace", num_samples as i32);
+        values
+            .entry("OriginalSamplesPerTrace")
+            .or_insert(num_samples as i32);
+        values.insert("DataSampleFormatCode", self.sample_format.format_code());
+        values.insert("SegyFormatRevisionNumber", (revision as i32) << 8);
+        values.insert("FixedLengthTraceFlag", 1);
+        values.insert("ExtendedTextualHeaders", 0);
+        if revision >= 2 {
+            values.insert("ExtendedSamplesPerTrace", num_samples as i32);
+            values.insert("IntegerConstant", 0x0102_0304);
+        }
+
+        let mut header = vec![0u8; BINARY_HEADER_SIZE];
+        for (name, offset, width) in BINARY_HEADER_FIELDS {
+            if let Some(&value) = values.get(name) {
+                match width {
+                    2 => header[*offset..offset + 2].copy_from_slice(&(value as i16).to_be_bytes()),
+                    _ => header[*offset..offset + 4].copy_from_slice(&value.to_be_bytes()),
+                }
+            }
+        }
+
+        if revision >= 2 {
+            // Extended sample interval (bytes 3273-3280) is an IEEE double
+            header[72..80].copy_from_slice(&(sample_interval as f64).to_be_bytes());
+        }
+
+        header
+    }
+}
+
+impl VolumeDataAccess {
+    /// Store the original SEG-Y trace headers of the volume
+    ///
+    /// `headers` holds one 240-byte header per trace, inline-major over the inline/crossline grid.
+    pub async fn write_segy_trace_headers(&self, headers: &[u8]) -> Result<()> {
+        let axes = self.layout().seismic_axes()?;
+        let layout = self.layout();
+        let expected = layout.axes[axes.inline].num_samples
+            * layout.axes[axes.crossline].num_samples
+            * TRACE_HEADER_SIZE;
+        if headers.len() != expected {
+            return Err(VdsError::InvalidDimensions(format!(
+                "Trace headers have {} bytes, expected {}",
+                headers.len(),
+                expected
+            )));
+        }
+
+        self.io_manager().write(TRACE_HEADERS_PATH, headers).await
+    }
+
+    /// Read the stored SEG-Y trace headers, if any
+    pub async fn read_segy_trace_headers(&self) -> Result<Option<Bytes>> {
+        if !self.io_manager().exists(TRACE_HEADERS_PATH).await? {
+            return Ok(None);
+        }
+        Ok(Some(self.io_manager().read(TRACE_HEADERS_PATH).await?))
+    }
+}
+
+/// Resolve 1-based trace header byte positions, applying the stored mappings
+fn trace_header_positions(segy: Option<&SegyMetadata>) -> Result<HashMap<&'static str, usize>> {
+    let mut positions: HashMap<&'static str, usize> =
+        DEFAULT_TRACE_HEADER_POSITIONS.iter().copied().collect();
+
+    if let Some(segy) = segy {
+        for (name, default) in positions.iter_mut() {
+            if let Some(value) = segy.trace_header_mappings.get(*name) {
+                let byte: usize = value.trim().parse().map_err(|_| {
+                    VdsError::Metadata(format!(
+                        "Invalid trace header position '{}' for {}",
+                        value, name
+                    ))
+                })?;
+                if byte == 0 || byte + 3 > TRACE_HEADER_SIZE {
+                    return Err(VdsError::Metadata(format!(
+                        "Trace header position {} for {} is outside the header",
+                        byte, name
+                    )));
+                }
+                *default = byte;
+            }
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Synthesise a trace header from axis annotations and the survey transform
+fn synthesise_trace_header(
+    positions: &HashMap<&'static str, usize>,
+    inline_axis: &AxisDescriptor,
+    crossline_axis: &AxisDescriptor,
+    transform: Option<&SurveyTransform>,
+    inline_index: usize,
+    crossline_index: usize,
+    trace: usize,
+) -> [u8; TRACE_HEADER_SIZE] {
+    let mut header = [0u8; TRACE_HEADER_SIZE];
+
+    // CDP ensemble number and trace identification code (seismic data)
+    put_i32(&mut header, 21, (trace + 1) as i32);
+    put_i16(&mut header, 29, 1);
+
+    put_i32(
+        &mut header,
+        positions["Inline"],
+        inline_axis.index_to_coord(inline_index).round() as i32,
+    );
+    put_i32(
+        &mut header,
+        positions["Crossline"],
+        crossline_axis.index_to_coord(crossline_index).round() as i32,
+    );
+
+    if let Some(transform) = transform {
+        let [x, y] = transform.index_to_world(inline_index as f64, crossline_index as f64);
+        let scale = -(COORDINATE_SCALAR as f64);
+        put_i16(&mut header, 71, COORDINATE_SCALAR);
+        put_i32(&mut header, positions["CdpX"], (x * scale).round() as i32);
+        put_i32(&mut header, positions["CdpY"], (y * scale).round() as i32);
+    }
+
+    header
+}
+
+/// Sample interval in microseconds (or thousandths of a depth unit)
+fn sample_interval_micros(axis: &AxisDescriptor) -> u32 {
+    let factor = match axis.unit.to_ascii_lowercase().as_str() {
+        "s" => 1_000_000.0,
+        "us" | "µs" => 1.0,
+        _ => 1000.0,
+    };
+    (axis.step_size() * factor).round() as u32
+}
+
+/// Write a big-endian i32 at a 1-based byte position
+fn put_i32(header: &mut [u8], byte: usize, value: i32) {
+    header[byte - 1..byte + 3].copy_from_slice(&value.to_be_bytes());
+}
+
+/// Write a big-endian i16 at a 1-based byte position
+fn put_i16(header: &mut [u8], byte: usize, value: i16) {
+    header[byte - 1..byte + 1].copy_from_slice(&value.to_be_bytes());
+}
+
+/// Map an ASCII byte to EBCDIC (code page 037); unprintable bytes become spaces
+fn ascii_to_ebcdic(byte: u8) -> u8 {
+    match byte {
+        b'0'..=b'9' => 0xF0 + (byte - b'0'),
+        b'A'..=b'I' => 0xC1 + (byte - b'A'),
+        b'J'..=b'R' => 0xD1 + (byte - b'J'),
+        b'S'..=b'Z' => 0xE2 + (byte - b'S'),
+        b'a'..=b'i' => 0x81 + (byte - b'a'),
+        b'j'..=b'r' => 0x91 + (byte - b'j'),
+        b's'..=b'z' => 0xA2 + (byte - b's'),
+        b'!' => 0x5A,
+        b'"' => 0x7F,
+        b'#' => 0x7B,
+        b'$' => 0x5B,
+        b'%' => 0x6C,
+        b'&' => 0x50,
+        b'\'' => 0x7D,
+        b'(' => 0x4D,
+        b')' => 0x5D,
+        b'*' => 0x5C,
+        b'+' => 0x4E,
+        b',' => 0x6B,
+        b'-' => 0x60,
+        b'.' => 0x4B,
+        b'/' => 0x61,
+        b':' => 0x7A,
+        b';' => 0x5E,
+        b'<' => 0x4C,
+        b'=' => 0x7E,
+        b'>' => 0x6E,
+        b'?' => 0x6F,
+        b'@' => 0x7C,
+        b'[' => 0xBA,
+        b'\\' => 0xE0,
+        b']' => 0xBB,
+        b'^' => 0xB0,
+        b'_' => 0x6D,
+        b'`' => 0x79,
+        b'{' => 0xC0,
+        b'|' => 0x4F,
+        b'}' => 0xD0,
+        b'~' => 0xA1,
+        _ => 0x40,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::metadata::{SurveyMetadata, VdsMetadata};
+    use crate::types::DataType;
+    use crate::utils::f32_to_bytes;
+    use tempfile::TempDir;
+
+    const INLINES: usize = 5;
+    const CROSSLINES: usize = 3;
+    const SAMPLES: usize = 6;
+
+    async fn create_test_volume(dir: &TempDir, data_type: DataType) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(INLINES, "Inline", "unitless", 100.0, 104.0),
+            AxisDescriptor::new(CROSSLINES, "Crossline", "unitless", 20.0, 24.0),
+            AxisDescriptor::new(SAMPLES, "Time", "ms", 0.0, 20.0),
+        ];
+        let layout = VolumeDataLayout::new(3, data_type, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([2, 2, 4, 1, 1, 1]));
+
+        let mut survey = SurveyMetadata::new("Test Survey", "3D Seismic");
+        survey.transform = Some(SurveyTransform::new(
+            [1000.0, 5000.0],
+            [0.0, 12.5],
+            [25.0, 0.0],
+        ));
+        let metadata = VdsMetadata::new(layout).with_survey_metadata(survey);
+
+        let vds = VolumeDataAccess::create(dir.path().to_str().unwrap(), metadata)
+            .await
+            .unwrap();
+        let values: Vec<f32> = (0..INLINES * CROSSLINES * SAMPLES)
+            .map(|v| v as f32 * 0.5)
+            .collect();
+        vds.write_slice(
+            &[0, 0, 0],
+            &[INLINES, CROSSLINES, SAMPLES],
+            &f32_to_bytes(&values, data_type),
+        )
+        .await
+        .unwrap();
+        vds
+    }
+
+    fn be_i32(data: &[u8], byte: usize) -> i32 {
+        i32::from_be_bytes(data[byte - 1..byte + 3].try_into().unwrap())
+    }
+
+    fn be_i16(data: &[u8], byte: usize) -> i16 {
+        i16::from_be_bytes(data[byte - 1..byte + 1].try_into().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_export_full_volume() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir, DataType::F32).await;
+
+        let mut output = Vec::new();
+        let summary = SegyExporter::new(&vds)
+            .with_revision(2)
+            .export(&mut output)
+            .await
+            .unwrap();
+
+        let trace_size = TRACE_HEADER_SIZE + SAMPLES * 4;
+        assert_eq!(summary.traces, INLINES * CROSSLINES);
+        assert_eq!(output.len() as u64, summary.bytes_written);
+        assert_eq!(output.len(), 3600 + INLINES * CROSSLINES * trace_size);
+
+        // Textual header is EBCDIC: "C 1" -> C3 40 F1
+        assert_eq!(&output[..3], &[0xC3, 0x40, 0xF1]);
+
+        // Binary header
+        assert_eq!(be_i16(&output, 3217), 4000);
+        assert_eq!(be_i16(&output, 3221), SAMPLES as i16);
+        assert_eq!(be_i16(&output, 3225), 5);
+        assert_eq!(be_i16(&output, 3501), 0x0200);
+        assert_eq!(be_i32(&output, 3297), 0x0102_0304);
+
+        // Traces are in inline order with synthesised annotations and coordinates
+        for trace in 0..INLINES * CROSSLINES {
+            let (il, xl) = (trace / CROSSLINES, trace % CROSSLINES);
+            let header = &output[3600 + trace * trace_size..];
+            assert_eq!(be_i32(header, 189), 100 + il as i32);
+            assert_eq!(be_i32(header, 193), 20 + 2 * xl as i32);
+            assert_eq!(be_i16(header, 71), -100);
+            assert_eq!(
+                be_i32(header, 181),
+                (1000.0 + 25.0 * xl as f64) as i32 * 100
+            );
+            assert_eq!(
+                be_i32(header, 185),
+                ((5000.0 + 12.5 * il as f64) * 100.0) as i32
+            );
+
+            let samples = &header[TRACE_HEADER_SIZE..trace_size];
+            for k in 0..SAMPLES {
+                let value = f32::from_be_bytes(samples[k * 4..k * 4 + 4].try_into().unwrap());
+                assert_eq!(value, ((trace * SAMPLES + k) as f32) * 0.5);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_subvolume_with_stored_headers() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir, DataType::F32).await;
+
+        let mut headers = vec![0u8; INLINES * CROSSLINES * TRACE_HEADER_SIZE];
+        for (trace, header) in headers.chunks_exact_mut(TRACE_HEADER_SIZE).enumerate() {
+            put_i32(header, 9, 7000 + trace as i32);
+        }
+        vds.write_segy_trace_headers(&headers).await.unwrap();
+        assert!(vds.write_segy_trace_headers(&headers[1..]).await.is_err());
+
+        let mut output = Vec::new();
+        let summary = SegyExporter::new(&vds)
+            .with_sample_format(SegySampleFormat::IbmFloat)
+            .with_text_encoding(TextHeaderEncoding::Ascii)
+            .with_subvolume(&[1, 1, 2], &[3, 3, 5])
+            .export(&mut output)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.traces, 4);
+        assert_eq!(summary.samples_per_trace, 3);
+        assert_eq!(&output[..3], b"C 1");
+        assert_eq!(be_i16(&output, 3225), 1);
+        assert_eq!(be_i16(&output, 3501), 0x0100);
+
+        let trace_size = TRACE_HEADER_SIZE + 3 * 4;
+        let header = &output[3600 + trace_size..];
+        // Second trace is inline index 1, crossline index 2
+        assert_eq!(be_i32(header, 9), 7000 + (CROSSLINES + 2) as i32);
+        assert_eq!(be_i16(header, 115), 3);
+        assert_eq!(be_i16(header, 109), 8);
+        let first = u32::from_be_bytes(header[240..244].try_into().unwrap());
+        assert_eq!(
+            first,
+            ieee_to_ibm(((CROSSLINES + 2) * SAMPLES + 2) as f32 * 0.5)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_ibm_volume_passthrough() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir, DataType::IbmF32).await;
+
+        let mut ibm = Vec::new();
+        SegyExporter::new(&vds)
+            .with_sample_format(SegySampleFormat::IbmFloat)
+            .export(&mut ibm)
+            .await
+            .unwrap();
+        let mut ieee = Vec::new();
+        SegyExporter::new(&vds).export(&mut ieee).await.unwrap();
+
+        // Last sample of the last trace
+        let value = ((INLINES * CROSSLINES * SAMPLES - 1) as f32) * 0.5;
+        let end = ibm.len();
+        assert_eq!(&ibm[end - 4..], &ieee_to_ibm(value).to_be_bytes());
+        assert_eq!(&ieee[end - 4..], &value.to_be_bytes());
+    }
+
+    #[test]
+    fn test_ascii_to_ebcdic() {
+        let encoded: Vec<u8> = b"C 1 Az9".iter().map(|&b| ascii_to_ebcdic(b)).collect();
+        assert_eq!(encoded, vec![0xC3, 0x40, 0xF1, 0x40, 0xC1, 0xA9, 0xF9]);
+    }
+}
@@ -0,0 +1,283 @@
+//! Streaming statistics pass over every brick of a volume
+//!
+//! Bricks are decoded concurrently and reduced with mergeable running moments, so memory use is
+//! bounded by the number of bricks in flight rather than the volume size.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::metadata::{BrickMetadata, VolumeStatistics};
+use crate::types::ValueRange;
+use crate::utils::{brick_path, bytes_to_f64, calculate_checksum};
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+/// Number of bricks decoded concurrently
+const MAX_CONCURRENT_BRICKS: usize = 16;
+
+/// Running min/max/moments over a set of samples, mergeable across bricks
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    count: u64,
+    nan_count: u64,
+    inf_count: u64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    /// Sum of squared deviations from the mean
+    m2: f64,
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            nan_count: 0,
+            inf_count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+}
+
+impl Accumulator {
+    fn push(&mut self, value: f64) {
+        if value.is_nan() {
+            self.nan_count += 1;
+        } else if value.is_infinite() {
+            self.inf_count += 1;
+        } else {
+            self.count += 1;
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+            let delta = value - self.mean;
+            self.mean += delta / self.count as f64;
+            self.m2 += delta * (value - self.mean);
+        }
+    }
+
+    /// Combine with another accumulator (Chan et al. parallel variance)
+    fn merge(&mut self, other: &Accumulator) {
+        self.nan_count += other.nan_count;
+        self.inf_count += other.inf_count;
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            self.count = other.count;
+            self.min = other.min;
+            self.max = other.max;
+            self.mean = other.mean;
+            self.m2 = other.m2;
+            return;
+        }
+
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 +=
+            other.m2 + delta * delta * (self.count as f64 * other.count as f64) / count as f64;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.count = count;
+    }
+
+    fn value_range(&self) -> Option<ValueRange> {
+        (self.count > 0).then(|| ValueRange::new(self.min, self.max))
+    }
+
+    /// Final statistics; all moments are zero when there are no finite samples
+    fn statistics(&self) -> VolumeStatistics {
+        let (min, max, mean, std_dev, rms) = if self.count == 0 {
+            (0.0, 0.0, 0.0, 0.0, 0.0)
+        } else {
+            let variance = self.m2 / self.count as f64;
+            (
+                self.min,
+                self.max,
+                self.mean,
+                variance.sqrt(),
+                (variance + self.mean * self.mean).sqrt(),
+            )
+        };
+        VolumeStatistics {
+            count: self.count,
+            nan_count: self.nan_count,
+            inf_count: self.inf_count,
+            min,
+            max,
+            mean,
+            std_dev,
+            rms,
+        }
+    }
+}
+
+impl VolumeDataAccess {
+    /// Compute sample statistics over every stored brick and persist them
+    ///
+    /// Updates [`VdsMetadata::value_range`](crate::metadata::VdsMetadata::value_range) and
+    /// `statistics` in `metadata.json`, and writes a brick index with the size, checksum and
+    /// value range of each brick. Bricks that have never been written are skipped.
+    pub async fn compute_statistics(&self) -> Result<VolumeStatistics> {
+        let layout = self.layout();
+        let data_type = layout.data_type;
+
+        let scans = stream::iter(0..layout.total_bricks())
+            .map(|index| {
+                let layout = &layout;
+                async move {
+                    let path = brick_path(index, 0);
+                    if !self.io_manager().exists(&path).await? {
+                        return Ok::<_, VdsError>(None);
+                    }
+                    let compressed = self.io_manager().read(&path).await?;
+                    let data = self.decode_brick(&compressed, layout.brick_data_bytes(index))?;
+
+                    let mut accumulator = Accumulator::default();
+                    for value in bytes_to_f64(&data, data_type)? {
+                        accumulator.push(value);
+                    }
+
+                    let mut brick = BrickMetadata::new(index, compressed.len(), data.len());
+                    brick.checksum = Some(calculate_checksum(&data));
+                    brick.value_range = accumulator.value_range();
+                    Ok(Some((brick, accumulator)))
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_BRICKS);
+
+        let (mut bricks, total) = scans
+            .try_fold(
+                (Vec::new(), Accumulator::default()),
+                |(mut bricks, mut total), scan| {
+                    if let Some((brick, accumulator)) = scan {
+                        total.merge(&accumulator);
+                        bricks.push(brick);
+                    }
+                    futures::future::ready(Ok((bricks, total)))
+                },
+            )
+            .await?;
+        bricks.sort_by_key(|brick| brick.index);
+
+        let statistics = total.statistics();
+        self.write_brick_index(&bricks).await?;
+        self.update_metadata(|metadata| {
+            if let Some(range) = total.value_range() {
+                metadata.value_range = range;
+            }
+            metadata.statistics = Some(statistics);
+        })
+        .await?;
+
+        Ok(statistics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::metadata::VdsMetadata;
+    use crate::types::{AxisDescriptor, DataType};
+    use crate::utils::typed_data_to_bytes;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_accumulator_merge() {
+        let values = [1.0, -4.0, 2.5, f64::NAN, 8.0, f64::INFINITY, 0.5];
+        let mut whole = Accumulator::default();
+        values.iter().for_each(|&v| whole.push(v));
+
+        let mut left = Accumulator::default();
+        let mut right = Accumulator::default();
+        values[..3].iter().for_each(|&v| left.push(v));
+        values[3..].iter().for_each(|&v| right.push(v));
+        left.merge(&right);
+
+        let (a, b) = (whole.statistics(), left.statistics());
+        assert_eq!((a.count, a.nan_count, a.inf_count), (5, 1, 1));
+        assert_eq!((a.min, a.max), (-4.0, 8.0));
+        assert!((a.mean - 1.6).abs() < 1e-12 && (b.mean - 1.6).abs() < 1e-12);
+        assert!((a.std_dev - b.std_dev).abs() < 1e-12);
+        assert!((a.rms - (87.5f64 / 5.0).sqrt()).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn test_compute_statistics() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(4, "Inline", "unitless", 0.0, 3.0),
+            AxisDescriptor::new(6, "Sample", "ms", 0.0, 20.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([2, 4, 1, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(path, VdsMetadata::new(layout))
+            .await
+            .unwrap();
+
+        // Only the first brick row is written
+        let mut values: Vec<f32> = (0..12).map(|v| v as f32 - 2.0).collect();
+        values[5] = f32::NAN;
+        vds.write_slice(&[0, 0], &[2, 6], &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+
+        let stats = vds.compute_statistics().await.unwrap();
+        assert_eq!((stats.count, stats.nan_count, stats.inf_count), (11, 1, 0));
+        assert_eq!((stats.min, stats.max), (-2.0, 9.0));
+
+        // Persisted to metadata and the brick index
+        let reopened = VolumeDataAccess::open(path).await.unwrap();
+        let summary = reopened.get_stats().await;
+        assert_eq!(summary.statistics, Some(stats));
+        assert_eq!(summary.value_range.max, 9.0);
+
+        let index = reopened.read_brick_index().await.unwrap().unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index[1].index, 1);
+        let range = index[1].value_range.unwrap();
+        assert_eq!((range.min, range.max), (2.0, 9.0));
+    }
+
+    #[tokio::test]
+    async fn test_write_invalidates_statistics() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(4, "Inline", "unitless", 0.0, 3.0),
+            AxisDescriptor::new(4, "Sample", "ms", 0.0, 12.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([2, 4, 1, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(path, VdsMetadata::new(layout))
+            .await
+            .unwrap();
+        vds.write_slice(&[0, 0], &[4, 4], &typed_data_to_bytes(&[1.0f32; 16]))
+            .await
+            .unwrap();
+
+        // The unset default range is replaced, not widened to include zero
+        assert_eq!(vds.get_stats().await.value_range, ValueRange::new(1.0, 1.0));
+        vds.compute_statistics().await.unwrap();
+
+        vds.write_slice(&[0, 0], &[2, 4], &typed_data_to_bytes(&[50.0f32; 8]))
+            .await
+            .unwrap();
+        let summary = VolumeDataAccess::open(path)
+            .await
+            .unwrap()
+            .get_stats()
+            .await;
+        assert_eq!(summary.statistics, None);
+        assert_eq!(summary.value_range, ValueRange::new(1.0, 50.0));
+        assert!(vds.read_brick_index().await.unwrap().is_none());
+
+        let stats = vds.compute_statistics().await.unwrap();
+        assert_eq!((stats.min, stats.max, stats.mean), (1.0, 50.0, 25.5));
+    }
+}
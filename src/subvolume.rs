@@ -0,0 +1,348 @@
+//! Cropping a volume into a new VDS
+//!
+//! The extracted volume keeps the source brick size and codec. When the box starts on brick
+//! boundaries and ends on brick boundaries (or at the volume edge), every destination brick is
+//! a source brick, and compressed bricks are copied without being decoded.
+//!
+//! Sparse sources are cropped the same way on either path: a destination brick overlapping
+//! no stored source brick is left unwritten, and otherwise source bricks that were never
+//! written read as zeros.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::layout::VolumeDataLayout;
+use crate::segy::{TRACE_HEADERS_PATH, TRACE_HEADER_SIZE};
+use crate::utils::brick_path;
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+/// Number of bricks copied concurrently
+const MAX_CONCURRENT_BRICKS: usize = 16;
+
+impl VolumeDataAccess {
+    /// Write the voxel box `[min_coords, max_coords)` to a new volume at `destination_url`
+    ///
+    /// Axis coordinate ranges are rebased to the box, survey and SEG-Y metadata are copied with
+    /// the survey origin moved to the first inline/crossline of the box, and stored SEG-Y trace
+    /// headers are cropped to match. Cached statistics and histograms are not carried over.
+    /// Destination bricks covering only unwritten source bricks are left unwritten.
+    pub async fn extract_subvolume(
+        &self,
+        min_coords: &[usize],
+        max_coords: &[usize],
+        destination_url: impl Into<String>,
+    ) -> Result<VolumeDataAccess> {
+        let source = self.metadata();
+        let layout = &source.layout;
+        VolumeDataAccess::validate_box(layout, min_coords, max_coords)?;
+
+        let mut metadata = source.clone();
+        metadata.layout = cropped_layout(layout, min_coords, max_coords);
+        metadata.statistics = None;
+        metadata.histograms.clear();
+        metadata.touch();
+        metadata.created_at = metadata.modified_at;
+
+        let seismic_axes = layout.seismic_axes().ok();
+        if let (Some(axes), Some(survey)) = (seismic_axes, metadata.survey_metadata.as_mut()) {
+            if let Some(transform) = survey.transform.as_mut() {
+                transform.origin = transform.index_to_world(
+                    min_coords[axes.inline] as f64,
+                    min_coords[axes.crossline] as f64,
+                );
+            }
+        }
+
+        let destination = self.create_derived(destination_url, metadata).await?;
+        let target = destination.layout();
+
+        let aligned = (0..layout.dimensionality).all(|dim| {
+            let brick = layout.brick_size.get(dim);
+            min_coords[dim] % brick == 0
+                && (max_coords[dim] % brick == 0 || max_coords[dim] == layout.axes[dim].num_samples)
+        });
+
+        stream::iter(0..target.total_bricks())
+            .map(|index| {
+                let (target, destination) = (&target, &destination);
+                async move {
+                    let coords = target.brick_index_to_coords(index);
+                    if aligned {
+                        let source_coords: Vec<usize> = coords
+                            .iter()
+                            .enumerate()
+                            .map(|(dim, &c)| c + min_coords[dim] / layout.brick_size.get(dim))
+                            .collect();
+                        let path = brick_path(layout.brick_coords_to_index(&source_coords), 0);
+                        if self.io_manager().exists(&path).await? {
+                            let compressed = self.io_manager().read(&path).await?;
+                            destination
+                                .io_manager()
+                                .write(&brick_path(index, 0), &compressed)
+                                .await?;
+                        }
+                        return Ok(());
+                    }
+
+                    let range = target.brick_data_range(&coords);
+                    let source_min: Vec<usize> = range
+                        .iter()
+                        .zip(min_coords)
+                        .map(|(&(start, _), &offset)| start + offset)
+                        .collect();
+                    let source_max: Vec<usize> = range
+                        .iter()
+                        .zip(min_coords)
+                        .map(|(&(_, end), &offset)| end + offset)
+                        .collect();
+                    match self.read_stored_slice(&source_min, &source_max).await? {
+                        Some(data) => destination.write_brick(index, &data).await,
+                        None => Ok(()),
+                    }
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_BRICKS)
+            .try_collect::<Vec<()>>()
+            .await?;
+
+        if let (Some(axes), Some(headers)) = (seismic_axes, self.read_segy_trace_headers().await?) {
+            let crosslines = layout.axes[axes.crossline].num_samples;
+            let mut cropped = Vec::new();
+            for il in min_coords[axes.inline]..max_coords[axes.inline] {
+                let start = (il * crosslines + min_coords[axes.crossline]) * TRACE_HEADER_SIZE;
+                let end = (il * crosslines + max_coords[axes.crossline]) * TRACE_HEADER_SIZE;
+                cropped.extend_from_slice(headers.get(start..end).ok_or_else(|| {
+                    VdsError::InvalidFormat(format!(
+                        "{} is too short for the volume",
+                        TRACE_HEADERS_PATH
+                    ))
+                })?);
+            }
+            destination.write_segy_trace_headers(&cropped).await?;
+        }
+
+        Ok(destination)
+    }
+}
+
+/// Layout of the box `[min_coords, max_coords)` with axis coordinates rebased to it
+fn cropped_layout(
+    layout: &VolumeDataLayout,
+    min_coords: &[usize],
+    max_coords: &[usize],
+) -> VolumeDataLayout {
+    let mut cropped = layout.clone();
+    cropped.lod_levels = 1;
+    for (dim, axis) in cropped.axes.iter_mut().enumerate() {
+        let source = &layout.axes[dim];
+        axis.num_samples = max_coords[dim] - min_coords[dim];
+        axis.coord_min = source.index_to_coord(min_coords[dim]);
+        axis.coord_max = source.index_to_coord(max_coords[dim] - 1);
+    }
+    cropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::BrickSize;
+    use crate::metadata::{SegyMetadata, SurveyMetadata, SurveyTransform, VdsMetadata};
+    use crate::types::{AxisDescriptor, DataType};
+    use crate::utils::typed_data_to_bytes;
+    use tempfile::TempDir;
+
+    const SIZE: [usize; 3] = [10, 9, 8];
+
+    fn value(il: usize, xl: usize, s: usize) -> f32 {
+        (il * 100 + xl * 10 + s) as f32
+    }
+
+    async fn create_test_volume(dir: &TempDir) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(SIZE[0], "Inline", "unitless", 100.0, 118.0),
+            AxisDescriptor::new(SIZE[1], "Crossline", "unitless", 300.0, 308.0),
+            AxisDescriptor::new(SIZE[2], "Sample", "ms", 0.0, 28.0),
+        ];
+        let layout = VolumeDataLayout::new(3, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 4, 4, 1, 1, 1]));
+        let mut survey = SurveyMetadata::new("Crop", "3D Seismic");
+        survey.transform = Some(SurveyTransform::new(
+            [1000.0, 2000.0],
+            [25.0, 0.0],
+            [0.0, 12.5],
+        ));
+        survey.segy_metadata = Some(SegyMetadata::new(2));
+        let metadata = VdsMetadata::new(layout).with_survey_metadata(survey);
+        let path = dir.path().join("source");
+        let vds = VolumeDataAccess::create(path.to_str().unwrap(), metadata)
+            .await
+            .unwrap();
+
+        let mut values = Vec::new();
+        for il in 0..SIZE[0] {
+            for xl in 0..SIZE[1] {
+                for s in 0..SIZE[2] {
+                    values.push(value(il, xl, s));
+                }
+            }
+        }
+        vds.write_slice(&[0, 0, 0], &SIZE, &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+
+        // Each trace header records its own trace number
+        let mut headers = vec![0u8; SIZE[0] * SIZE[1] * TRACE_HEADER_SIZE];
+        for (trace, header) in headers.chunks_mut(TRACE_HEADER_SIZE).enumerate() {
+            header[..4].copy_from_slice(&(trace as u32).to_be_bytes());
+        }
+        vds.write_segy_trace_headers(&headers).await.unwrap();
+        vds
+    }
+
+    async fn assert_contents(vds: &VolumeDataAccess, min: &[usize], max: &[usize]) {
+        let dims: Vec<usize> = min.iter().zip(max).map(|(a, b)| b - a).collect();
+        let data = vds.read_slice_f32(&[0, 0, 0], &dims).await.unwrap();
+        let mut i = 0;
+        for il in min[0]..max[0] {
+            for xl in min[1]..max[1] {
+                for s in min[2]..max[2] {
+                    assert_eq!(data[i], value(il, xl, s));
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extract_aligned_subvolume() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+        let (min, max) = ([4, 0, 4], [10, 8, 8]);
+
+        let path = dir.path().join("aligned");
+        let crop = vds
+            .extract_subvolume(&min, &max, path.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_contents(&crop, &min, &max).await;
+
+        // Bricks are copied verbatim
+        let source_index = vds.layout().brick_coords_to_index(&[1, 0, 1]);
+        assert_eq!(
+            crop.io_manager().read(&brick_path(0, 0)).await.unwrap(),
+            vds.io_manager()
+                .read(&brick_path(source_index, 0))
+                .await
+                .unwrap()
+        );
+
+        let layout = crop.layout();
+        assert_eq!(layout.size(), vec![6, 8, 4]);
+        assert_eq!(layout.axes[0].coord_min, 108.0);
+        assert_eq!(layout.axes[0].coord_max, 118.0);
+        assert_eq!(layout.axes[2].coord_min, 16.0);
+
+        let survey = crop.metadata().survey_metadata.unwrap();
+        assert_eq!(survey.transform.unwrap().origin, [1100.0, 2000.0]);
+        assert_eq!(survey.segy_metadata.unwrap().revision, 2);
+    }
+
+    #[tokio::test]
+    async fn test_extract_unaligned_subvolume() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+        let (min, max) = ([1, 2, 3], [7, 9, 6]);
+
+        let path = dir.path().join("unaligned");
+        let crop = vds
+            .extract_subvolume(&min, &max, path.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_contents(&crop, &min, &max).await;
+        assert_eq!(crop.layout().axes[1].coord_min, 302.0);
+        assert_eq!(
+            crop.metadata()
+                .survey_metadata
+                .unwrap()
+                .transform
+                .unwrap()
+                .origin,
+            [1025.0, 2025.0]
+        );
+
+        // Trace headers of the first and last trace in the box
+        let headers = crop.read_segy_trace_headers().await.unwrap().unwrap();
+        assert_eq!(headers.len(), 6 * 7 * TRACE_HEADER_SIZE);
+        assert_eq!(&headers[..4], &(SIZE[1] as u32 + 2).to_be_bytes());
+        let last = headers.len() - TRACE_HEADER_SIZE;
+        assert_eq!(
+            &headers[last..last + 4],
+            &(6 * SIZE[1] as u32 + 8).to_be_bytes()
+        );
+
+        assert!(vds
+            .extract_subvolume(&[0, 0, 0], &[11, 9, 8], path.to_str().unwrap())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_extract_sparse_subvolume() {
+        let dir = TempDir::new().unwrap();
+        let axes = vec![
+            AxisDescriptor::new(SIZE[0], "Inline", "unitless", 100.0, 118.0),
+            AxisDescriptor::new(SIZE[1], "Crossline", "unitless", 300.0, 308.0),
+            AxisDescriptor::new(SIZE[2], "Sample", "ms", 0.0, 28.0),
+        ];
+        let layout = VolumeDataLayout::new(3, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 4, 4, 1, 1, 1]));
+        let path = dir.path().join("source");
+        let vds = VolumeDataAccess::create(path.to_str().unwrap(), VdsMetadata::new(layout))
+            .await
+            .unwrap();
+
+        // Only the first source brick is written
+        let mut values = Vec::new();
+        for il in 0..4 {
+            for xl in 0..4 {
+                for s in 0..4 {
+                    values.push(value(il, xl, s));
+                }
+            }
+        }
+        vds.write_slice(&[0, 0, 0], &[4, 4, 4], &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+
+        // The aligned box ends at the volume edge, so its bricks are copied verbatim
+        for (name, min, max) in [
+            ("aligned", [0, 0, 0], [10, 9, 8]),
+            ("unaligned", [2, 2, 2], [9, 9, 8]),
+        ] {
+            let path = dir.path().join(name);
+            let crop = vds
+                .extract_subvolume(&min, &max, path.to_str().unwrap())
+                .await
+                .unwrap();
+            let last = crop.layout().total_bricks() - 1;
+            assert!(crop.io_manager().exists(&brick_path(0, 0)).await.unwrap());
+            assert!(!crop
+                .io_manager()
+                .exists(&brick_path(last, 0))
+                .await
+                .unwrap());
+
+            let data = crop.read_slice_f32(&[0, 0, 0], &[2, 2, 2]).await.unwrap();
+            assert_eq!(data[7], value(min[0] + 1, min[1] + 1, min[2] + 1));
+        }
+
+        // Unwritten source bricks read as zeros next to stored ones
+        let crop = VolumeDataAccess::open(dir.path().join("unaligned").to_str().unwrap())
+            .await
+            .unwrap();
+        let data = crop.read_slice_f32(&[1, 1, 1], &[3, 3, 3]).await.unwrap();
+        assert_eq!(data[0], value(3, 3, 3));
+        assert_eq!(data[7], 0.0);
+    }
+}
@@ -29,23 +29,44 @@ pub enum DataType {
     F32 = 9,
     /// 64-bit floating point
     F64 = 10,
+    /// 32-bit IBM System/360 floating point, stored big-endian
+    IbmF32 = 11,
 }
 
 impl DataType {
+    /// Get the data type from a byte value
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(DataType::U1),
+            1 => Some(DataType::U8),
+            2 => Some(DataType::U16),
+            3 => Some(DataType::U32),
+            4 => Some(DataType::U64),
+            5 => Some(DataType::I8),
+            6 => Some(DataType::I16),
+            7 => Some(DataType::I32),
+            8 => Some(DataType::I64),
+            9 => Some(DataType::F32),
+            10 => Some(DataType::F64),
+            11 => Some(DataType::IbmF32),
+            _ => None,
+        }
+    }
+
     /// Size in bytes of this data type
     pub fn size_in_bytes(&self) -> usize {
         match self {
             DataType::U1 => 1, // Stored as full bytes
             DataType::U8 | DataType::I8 => 1,
             DataType::U16 | DataType::I16 => 2,
-            DataType::U32 | DataType::I32 | DataType::F32 => 4,
+            DataType::U32 | DataType::I32 | DataType::F32 | DataType::IbmF32 => 4,
             DataType::U64 | DataType::I64 | DataType::F64 => 8,
         }
     }
 
     /// Check if this is a floating point type
     pub fn is_float(&self) -> bool {
-        matches!(self, DataType::F32 | DataType::F64)
+        matches!(self, DataType::F32 | DataType::F64 | DataType::IbmF32)
     }
 
     /// Check if this is an integer type
@@ -140,11 +161,39 @@ impl AxisDescriptor {
         }
     }
 
+    /// Same axis range sampled every `step`, keeping `coord_min`
+    ///
+    /// The last sample is the last whole step that doesn't pass `coord_max`.
+    pub fn with_step(&self, step: f64) -> Self {
+        let span = self.coord_max - self.coord_min;
+        let intervals = if step == 0.0 || span == 0.0 {
+            0
+        } else {
+            // Tolerate rounding in the span, e.g. 0..1000 ms at 4 ms
+            (span / step + 1e-9).floor().max(0.0) as usize
+        };
+        Self {
+            num_samples: intervals + 1,
+            coord_max: self.coord_min + intervals as f64 * step,
+            ..self.clone()
+        }
+    }
+
     /// Convert sample index to coordinate
     pub fn index_to_coord(&self, index: usize) -> f64 {
         self.coord_min + index as f64 * self.step_size()
     }
 
+    /// Convert coordinate to a fractional sample index, without rounding or clamping
+    pub fn coord_to_fractional_index(&self, coord: f64) -> f64 {
+        let step = self.step_size();
+        if step == 0.0 {
+            0.0
+        } else {
+            (coord - self.coord_min) / step
+        }
+    }
+
     /// Convert coordinate to sample index (nearest)
     pub fn coord_to_index(&self, coord: f64) -> usize {
         let normalized = (coord - self.coord_min) / self.step_size();
@@ -153,10 +202,38 @@ impl AxisDescriptor {
             .max(0.0)
             .min((self.num_samples - 1) as f64) as usize
     }
+
+    /// Convert a coordinate to the nearest sample index, or `None` if it lies outside the axis
+    ///
+    /// Coordinates within half a step of either end are accepted.
+    pub fn try_coord_to_index(&self, coord: f64) -> Option<usize> {
+        let tolerance = self.step_size().abs() / 2.0;
+        let (low, high) = if self.coord_min <= self.coord_max {
+            (self.coord_min, self.coord_max)
+        } else {
+            (self.coord_max, self.coord_min)
+        };
+        if !coord.is_finite() || coord < low - tolerance || coord > high + tolerance {
+            return None;
+        }
+        if self.num_samples <= 1 {
+            return Some(0);
+        }
+        Some(self.coord_to_index(coord))
+    }
+
+    /// Index of the sample at `coord`, or `None` if it lies outside the axis or between samples
+    ///
+    /// Coordinates within a millionth of a step of a sample are accepted.
+    pub fn try_sample_index(&self, coord: f64) -> Option<usize> {
+        let index = self.try_coord_to_index(coord)?;
+        let tolerance = 1e-6 * self.step_size().abs().max(f64::EPSILON * coord.abs());
+        ((coord - self.index_to_coord(index)).abs() <= tolerance).then_some(index)
+    }
 }
 
 /// Value range for a volume
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct ValueRange {
     pub min: f64,
     pub max: f64,
@@ -170,6 +247,21 @@ impl ValueRange {
     pub fn is_valid(&self) -> bool {
         self.min.is_finite() && self.max.is_finite() && self.min <= self.max
     }
+
+    /// Range of the finite values, or `None` if there are none
+    pub fn of_finite(values: &[f64]) -> Option<Self> {
+        values
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold(None, |range: Option<Self>, &v| {
+                Some(range.map_or(Self::new(v, v), |r| Self::new(r.min.min(v), r.max.max(v))))
+            })
+    }
+
+    /// Whether `other` lies within this range
+    pub fn covers(&self, other: &ValueRange) -> bool {
+        self.min <= other.min && self.max >= other.max
+    }
 }
 
 #[cfg(test)]
@@ -182,6 +274,8 @@ mod tests {
         assert_eq!(DataType::U16.size_in_bytes(), 2);
         assert_eq!(DataType::F32.size_in_bytes(), 4);
         assert_eq!(DataType::F64.size_in_bytes(), 8);
+        assert_eq!(DataType::IbmF32.size_in_bytes(), 4);
+        assert!(DataType::IbmF32.is_float());
     }
 
     #[test]
@@ -201,5 +295,18 @@ mod tests {
         assert_eq!(axis.index_to_coord(0), 0.0);
         assert_eq!(axis.index_to_coord(100), 1000.0);
         assert_eq!(axis.coord_to_index(500.0), 50);
+
+        assert_eq!(axis.coord_to_fractional_index(505.0), 50.5);
+        assert_eq!(axis.try_coord_to_index(504.0), Some(50));
+        assert_eq!(axis.try_coord_to_index(1004.0), Some(100));
+        assert_eq!(axis.try_coord_to_index(1006.0), None);
+        assert_eq!(axis.try_coord_to_index(-6.0), None);
+        assert_eq!(axis.try_sample_index(500.0), Some(50));
+        assert_eq!(axis.try_sample_index(504.0), None);
+
+        let resampled = axis.with_step(4.0);
+        assert_eq!(resampled.num_samples, 251);
+        assert_eq!(resampled.coord_max, 1000.0);
+        assert_eq!(axis.with_step(30.0).coord_max, 990.0);
     }
 }
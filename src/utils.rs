@@ -1,6 +1,7 @@
 //! Utility functions
 
 use crate::error::{Result, VdsError};
+use crate::types::DataType;
 use std::mem;
 
 /// Convert raw bytes to typed data
@@ -39,23 +40,198 @@ pub fn typed_data_to_bytes<T: Copy>(data: &[T]) -> Vec<u8> {
     bytes
 }
 
-/// Calculate checksum (CRC32) for data
-pub fn calculate_checksum(data: &[u8]) -> u32 {
-    // Simple CRC32 implementation
-    let mut crc = 0xFFFFFFFFu32;
-
-    for &byte in data {
-        crc ^= byte as u32;
-        for _ in 0..8 {
-            if crc & 1 != 0 {
-                crc = (crc >> 1) ^ 0xEDB88320;
-            } else {
-                crc >>= 1;
+/// Decode raw voxel bytes of the given data type into `f32` samples
+pub fn bytes_to_f32(bytes: &[u8], data_type: DataType) -> Result<Vec<f32>> {
+    Ok(bytes_to_f64(bytes, data_type)?
+        .into_iter()
+        .map(|v| v as f32)
+        .collect())
+}
+
+/// Decode raw voxel bytes of the given data type into `f64` samples
+pub fn bytes_to_f64(bytes: &[u8], data_type: DataType) -> Result<Vec<f64>> {
+    let size = data_type.size_in_bytes();
+    if bytes.len() % size != 0 {
+        return Err(VdsError::InvalidFormat(
+            "Byte length not aligned with data type size".to_string(),
+        ));
+    }
+
+    macro_rules! decode {
+        ($t:ty) => {
+            bytes
+                .chunks_exact(size)
+                .map(|c| <$t>::from_ne_bytes(c.try_into().unwrap()) as f64)
+                .collect()
+        };
+    }
+
+    Ok(match data_type {
+        DataType::U1 | DataType::U8 => bytes.iter().map(|&b| b as f64).collect(),
+        DataType::I8 => bytes.iter().map(|&b| b as i8 as f64).collect(),
+        DataType::U16 => decode!(u16),
+        DataType::I16 => decode!(i16),
+        DataType::U32 => decode!(u32),
+        DataType::I32 => decode!(i32),
+        DataType::U64 => decode!(u64),
+        DataType::I64 => decode!(i64),
+        DataType::F32 => decode!(f32),
+        DataType::F64 => decode!(f64),
+        DataType::IbmF32 => crate::ibm::decode_be(bytes)
+            .into_iter()
+            .map(|v| v as f64)
+            .collect(),
+    })
+}
+
+/// Encode `f32` samples as raw voxel bytes of the given data type
+///
+/// Integer types are rounded and saturated to their representable range.
+pub fn f32_to_bytes(values: &[f32], data_type: DataType) -> Vec<u8> {
+    match data_type {
+        DataType::F32 => values.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+        DataType::IbmF32 => crate::ibm::encode_be(values),
+        _ => f64_to_bytes(
+            &values.iter().map(|&v| v as f64).collect::<Vec<_>>(),
+            data_type,
+        ),
+    }
+}
+
+/// Encode `f64` samples as raw voxel bytes of the given data type
+///
+/// Integer types are rounded and saturated to their representable range.
+pub fn f64_to_bytes(values: &[f64], data_type: DataType) -> Vec<u8> {
+    macro_rules! encode {
+        ($t:ty) => {
+            values
+                .iter()
+                .flat_map(|&v| (v.round() as $t).to_ne_bytes())
+                .collect()
+        };
+    }
+
+    match data_type {
+        DataType::U1 | DataType::U8 => encode!(u8),
+        DataType::I8 => encode!(i8),
+        DataType::U16 => encode!(u16),
+        DataType::I16 => encode!(i16),
+        DataType::U32 => encode!(u32),
+        DataType::I32 => encode!(i32),
+        DataType::U64 => encode!(u64),
+        DataType::I64 => encode!(i64),
+        DataType::F32 => values
+            .iter()
+            .flat_map(|&v| (v as f32).to_ne_bytes())
+            .collect(),
+        DataType::F64 => values.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+        DataType::IbmF32 => {
+            crate::ibm::encode_be(&values.iter().map(|&v| v as f32).collect::<Vec<_>>())
+        }
+    }
+}
+
+/// Origin and extent of a row-major buffer (last dimension fastest) in voxel coordinates
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BufferRegion<'a> {
+    pub origin: &'a [usize],
+    pub dims: &'a [usize],
+}
+
+impl BufferRegion<'_> {
+    fn offset(&self, coords: &[usize]) -> usize {
+        coords
+            .iter()
+            .zip(self.origin.iter().zip(self.dims.iter()))
+            .fold(0, |acc, (&c, (&o, &d))| acc * d + (c - o))
+    }
+
+    /// Voxel offset of `inner`, which must lie inside this region, if its voxels are one
+    /// contiguous run of this buffer
+    pub fn contiguous_offset(&self, inner: &BufferRegion<'_>) -> Option<usize> {
+        // Dimensions after the last partial one must span the region, those before it be flat
+        let contiguous = match (0..self.dims.len())
+            .rev()
+            .find(|&i| inner.dims[i] != self.dims[i])
+        {
+            Some(partial) => inner.dims[..partial].iter().all(|&d| d == 1),
+            None => true,
+        };
+        contiguous.then(|| self.offset(inner.origin))
+    }
+}
+
+/// Reusable scratch buffers, so repeated brick decodes do not allocate
+#[derive(Debug, Default)]
+pub(crate) struct BufferPool {
+    buffers: parking_lot::Mutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    /// Buffers kept for reuse; more are dropped when returned
+    const MAX_BUFFERS: usize = 16;
+
+    /// A buffer of `len` bytes with unspecified contents
+    pub fn take(&self, len: usize) -> Vec<u8> {
+        let mut buffer = self.buffers.lock().pop().unwrap_or_default();
+        buffer.resize(len, 0);
+        buffer
+    }
+
+    /// Return a buffer for reuse
+    pub fn give(&self, buffer: Vec<u8>) {
+        let mut buffers = self.buffers.lock();
+        if buffers.len() < Self::MAX_BUFFERS {
+            buffers.push(buffer);
+        }
+    }
+}
+
+/// Copy the voxel box `[box_min, box_max)` between two row-major buffers
+///
+/// Both buffers must contain the box; contiguous rows along the last dimension are copied at once.
+pub(crate) fn copy_box(
+    src: &[u8],
+    src_region: BufferRegion<'_>,
+    dst: &mut [u8],
+    dst_region: BufferRegion<'_>,
+    box_min: &[usize],
+    box_max: &[usize],
+    element_size: usize,
+) {
+    let n = box_min.len();
+    if n == 0 || box_min.iter().zip(box_max).any(|(min, max)| min >= max) {
+        return;
+    }
+
+    let row_bytes = (box_max[n - 1] - box_min[n - 1]) * element_size;
+    let mut coords = box_min.to_vec();
+
+    loop {
+        let src_start = src_region.offset(&coords) * element_size;
+        let dst_start = dst_region.offset(&coords) * element_size;
+        dst[dst_start..dst_start + row_bytes]
+            .copy_from_slice(&src[src_start..src_start + row_bytes]);
+
+        // Advance to the next row, last-but-one dimension fastest
+        let mut dim = n - 1;
+        loop {
+            if dim == 0 {
+                return;
+            }
+            dim -= 1;
+            coords[dim] += 1;
+            if coords[dim] < box_max[dim] {
+                break;
             }
+            coords[dim] = box_min[dim];
         }
     }
+}
 
-    !crc
+/// Calculate checksum (CRC32) for data
+pub fn calculate_checksum(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
 }
 
 /// Verify checksum
@@ -87,6 +263,11 @@ pub fn brick_path(index: usize, lod_level: usize) -> String {
     format!("bricks/lod{}/{:08}.brick", lod_level, index)
 }
 
+/// Path of the brick index for a level of detail
+pub fn brick_index_path(lod_level: usize) -> String {
+    format!("bricks/lod{}/index.json", lod_level)
+}
+
 /// Align value to power of 2
 pub fn align_to_power_of_2(value: usize, alignment: usize) -> usize {
     debug_assert!(alignment.is_power_of_two());
@@ -107,6 +288,86 @@ mod tests {
         assert_eq!(data, recovered);
     }
 
+    #[test]
+    fn test_sample_conversion() {
+        let values = [-3.0f32, 0.0, 1.5, 300.0];
+        let bytes = f32_to_bytes(&values, DataType::F32);
+        assert_eq!(bytes_to_f32(&bytes, DataType::F32).unwrap(), values);
+
+        // Integers round and saturate
+        let bytes = f32_to_bytes(&values, DataType::U8);
+        assert_eq!(bytes, vec![0, 0, 2, 255]);
+        let bytes = f32_to_bytes(&values, DataType::I16);
+        assert_eq!(
+            bytes_to_f64(&bytes, DataType::I16).unwrap(),
+            vec![-3.0, 0.0, 2.0, 300.0]
+        );
+
+        assert!(bytes_to_f32(&[0u8; 3], DataType::F32).is_err());
+
+        // IBM floats are stored as big-endian words
+        let bytes = f32_to_bytes(&[1.0, -118.625], DataType::IbmF32);
+        assert_eq!(bytes, vec![0x41, 0x10, 0, 0, 0xC2, 0x76, 0xA0, 0]);
+        assert_eq!(
+            bytes_to_f32(&bytes, DataType::IbmF32).unwrap(),
+            vec![1.0, -118.625]
+        );
+    }
+
+    #[test]
+    fn test_copy_box() {
+        // 3x4 source with values 0..12, copy the 2x2 box at (1, 1) into a 2x3 destination at (1, 0)
+        let src: Vec<u8> = (0..12).collect();
+        let mut dst = vec![0u8; 6];
+        copy_box(
+            &src,
+            BufferRegion {
+                origin: &[0, 0],
+                dims: &[3, 4],
+            },
+            &mut dst,
+            BufferRegion {
+                origin: &[1, 0],
+                dims: &[2, 3],
+            },
+            &[1, 1],
+            &[3, 3],
+            1,
+        );
+        assert_eq!(dst, vec![0, 5, 6, 0, 9, 10]);
+    }
+
+    #[test]
+    fn test_contiguous_offset() {
+        let outer = BufferRegion {
+            origin: &[10, 0, 0],
+            dims: &[8, 4, 6],
+        };
+        let slab = BufferRegion {
+            origin: &[12, 0, 0],
+            dims: &[3, 4, 6],
+        };
+        assert_eq!(outer.contiguous_offset(&slab), Some(2 * 24));
+        let row = BufferRegion {
+            origin: &[11, 2, 1],
+            dims: &[1, 1, 4],
+        };
+        assert_eq!(outer.contiguous_offset(&row), Some(24 + 2 * 6 + 1));
+        let column = BufferRegion {
+            origin: &[10, 0, 0],
+            dims: &[2, 2, 6],
+        };
+        assert_eq!(outer.contiguous_offset(&column), None);
+
+        let pool = BufferPool::default();
+        let buffer = pool.take(100);
+        assert_eq!(buffer.len(), 100);
+        let capacity = buffer.capacity();
+        pool.give(buffer);
+        let reused = pool.take(10);
+        assert_eq!((reused.len(), reused.capacity()), (10, capacity));
+    }
+
     #[test]
     fn test_checksum() {
         let data = b"Hello, world!";
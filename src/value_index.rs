@@ -0,0 +1,342 @@
+//! Brick min/max index for threshold and range queries
+//!
+//! The per-brick value ranges recorded by [`compute_statistics`](VolumeDataAccess::compute_statistics)
+//! tell which bricks could contain a value, so queries only decode the bricks that might match.
+
+use crate::access::VolumeDataAccess;
+use crate::error::{Result, VdsError};
+use crate::types::ValueRange;
+use crate::utils::bytes_to_f64;
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+/// Number of bricks decoded concurrently
+const MAX_CONCURRENT_BRICKS: usize = 16;
+
+/// Default cap on the number of voxels returned by a query
+pub const DEFAULT_MAX_VOXELS: usize = 1_000_000;
+
+/// Condition on finite sample values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueQuery {
+    /// Values strictly greater than the threshold
+    Above(f64),
+    /// Values strictly less than the threshold
+    Below(f64),
+    /// Values in the inclusive range `[min, max]`
+    Between(f64, f64),
+    /// Values with absolute value strictly greater than the threshold
+    AbsAbove(f64),
+}
+
+impl ValueQuery {
+    /// Whether `value` satisfies the query; NaN and infinities never match
+    pub fn matches(&self, value: f64) -> bool {
+        value.is_finite()
+            && match *self {
+                ValueQuery::Above(threshold) => value > threshold,
+                ValueQuery::Below(threshold) => value < threshold,
+                ValueQuery::Between(min, max) => value >= min && value <= max,
+                ValueQuery::AbsAbove(threshold) => value.abs() > threshold,
+            }
+    }
+
+    /// Whether a brick with finite values in `range` could contain a match
+    pub fn may_match(&self, range: &ValueRange) -> bool {
+        match *self {
+            ValueQuery::Above(threshold) => range.max > threshold,
+            ValueQuery::Below(threshold) => range.min < threshold,
+            ValueQuery::Between(min, max) => range.max >= min && range.min <= max,
+            ValueQuery::AbsAbove(threshold) => range.max > threshold || range.min < -threshold,
+        }
+    }
+}
+
+/// Per-brick value ranges of level 0
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrickValueIndex {
+    /// Range of finite values per brick; `None` for bricks not stored or without finite values
+    ranges: Vec<Option<ValueRange>>,
+}
+
+impl BrickValueIndex {
+    /// Value range of a brick
+    pub fn brick_range(&self, index: usize) -> Option<ValueRange> {
+        self.ranges.get(index).copied().flatten()
+    }
+
+    /// Number of bricks in the volume
+    pub fn total_bricks(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Indices of the bricks that could contain a match, in ascending order
+    pub fn candidate_bricks(&self, query: ValueQuery) -> Vec<usize> {
+        self.ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| range.is_some_and(|range| query.may_match(&range)))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Restrictions on a value query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryOptions {
+    /// Voxel box `[min, max)` to search, or the whole volume
+    pub region: Option<(Vec<usize>, Vec<usize>)>,
+    /// Stop collecting voxel coordinates after this many; matches are still counted
+    pub max_voxels: usize,
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        Self {
+            region: None,
+            max_voxels: DEFAULT_MAX_VOXELS,
+        }
+    }
+}
+
+impl QueryOptions {
+    /// Search the whole volume
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict the search to the voxel box `[min, max)`
+    pub fn with_region(mut self, min: Vec<usize>, max: Vec<usize>) -> Self {
+        self.region = Some((min, max));
+        self
+    }
+
+    /// Set the cap on returned voxels; 0 only counts matches
+    pub fn with_max_voxels(mut self, max_voxels: usize) -> Self {
+        self.max_voxels = max_voxels;
+        self
+    }
+}
+
+/// Voxels matching a value query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryMatches {
+    /// Matching voxel coordinates and values, in brick order then row-major within a brick
+    pub voxels: Vec<(Vec<usize>, f64)>,
+    /// Total number of matching voxels
+    pub count: u64,
+    /// Whether `voxels` was cut off at `max_voxels`
+    pub truncated: bool,
+    /// Bricks decoded
+    pub bricks_scanned: usize,
+    /// Bricks in the region skipped using the index
+    pub bricks_skipped: usize,
+}
+
+impl VolumeDataAccess {
+    /// Load the brick value index, running a statistics pass first if there is none
+    ///
+    /// Writing a brick removes the stored index, so it always reflects the stored bricks.
+    pub async fn value_index(&self) -> Result<BrickValueIndex> {
+        let bricks = match self.read_brick_index().await? {
+            Some(bricks) => bricks,
+            None => {
+                self.compute_statistics().await?;
+                self.read_brick_index().await?.ok_or_else(|| {
+                    VdsError::Metadata("Statistics pass did not write a brick index".to_string())
+                })?
+            }
+        };
+
+        let mut ranges = vec![None; self.layout().total_bricks()];
+        for brick in bricks {
+            if let Some(range) = ranges.get_mut(brick.index) {
+                *range = brick.value_range;
+            }
+        }
+        Ok(BrickValueIndex { ranges })
+    }
+
+    /// Find the voxels whose value satisfies `query`, skipping bricks that cannot match
+    pub async fn query_values(
+        &self,
+        query: ValueQuery,
+        options: &QueryOptions,
+    ) -> Result<QueryMatches> {
+        let layout = self.layout();
+        let (min_coords, max_coords) = match &options.region {
+            Some((min, max)) => {
+                VolumeDataAccess::validate_box(&layout, min, max)?;
+                (min.clone(), max.clone())
+            }
+            None => (vec![0; layout.dimensionality], layout.size()),
+        };
+
+        let index = self.value_index().await?;
+        let in_region: Vec<usize> = (0..layout.total_bricks())
+            .filter(|&brick| {
+                layout
+                    .brick_data_range(&layout.brick_index_to_coords(brick))
+                    .iter()
+                    .enumerate()
+                    .all(|(dim, &(start, end))| start < max_coords[dim] && end > min_coords[dim])
+            })
+            .collect();
+        let candidates: Vec<usize> = in_region
+            .iter()
+            .copied()
+            .filter(|&brick| {
+                index
+                    .brick_range(brick)
+                    .is_some_and(|range| query.may_match(&range))
+            })
+            .collect();
+
+        let mut matches = QueryMatches {
+            voxels: Vec::new(),
+            count: 0,
+            truncated: false,
+            bricks_scanned: candidates.len(),
+            bricks_skipped: in_region.len() - candidates.len(),
+        };
+
+        let mut scans = stream::iter(candidates)
+            .map(|brick| {
+                let (layout, min_coords, max_coords) = (&layout, &min_coords, &max_coords);
+                async move {
+                    let data = self.read_brick(brick).await?;
+                    let range = layout.brick_data_range(&layout.brick_index_to_coords(brick));
+                    let dims: Vec<usize> = range.iter().map(|(start, end)| end - start).collect();
+
+                    let mut found = Vec::new();
+                    for (i, value) in bytes_to_f64(&data, layout.data_type)?
+                        .into_iter()
+                        .enumerate()
+                    {
+                        if !query.matches(value) {
+                            continue;
+                        }
+                        let mut coords = vec![0; dims.len()];
+                        let mut rest = i;
+                        for dim in (0..dims.len()).rev() {
+                            coords[dim] = range[dim].0 + rest % dims[dim];
+                            rest /= dims[dim];
+                        }
+                        let inside = coords
+                            .iter()
+                            .enumerate()
+                            .all(|(dim, &c)| c >= min_coords[dim] && c < max_coords[dim]);
+                        if inside {
+                            found.push((coords, value));
+                        }
+                    }
+                    Ok::<_, VdsError>(found)
+                }
+            })
+            .buffered(MAX_CONCURRENT_BRICKS);
+
+        while let Some(found) = scans.try_next().await? {
+            matches.count += found.len() as u64;
+            let room = options.max_voxels - matches.voxels.len();
+            if found.len() > room {
+                matches.truncated = true;
+            }
+            matches.voxels.extend(found.into_iter().take(room));
+        }
+        Ok(matches)
+    }
+
+    /// Count the voxels whose value satisfies `query`, skipping bricks that cannot match
+    pub async fn count_values(&self, query: ValueQuery) -> Result<u64> {
+        let options = QueryOptions::new().with_max_voxels(0);
+        Ok(self.query_values(query, &options).await?.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BrickSize, VolumeDataLayout};
+    use crate::metadata::VdsMetadata;
+    use crate::types::{AxisDescriptor, DataType};
+    use crate::utils::typed_data_to_bytes;
+    use tempfile::TempDir;
+
+    /// 8x8 volume in 4x4 bricks, zero except for two bright voxels in brick 3
+    async fn create_test_volume(dir: &TempDir) -> VolumeDataAccess {
+        let axes = vec![
+            AxisDescriptor::new(8, "Inline", "unitless", 0.0, 7.0),
+            AxisDescriptor::new(8, "Sample", "ms", 0.0, 28.0),
+        ];
+        let layout = VolumeDataLayout::new(2, DataType::F32, axes)
+            .unwrap()
+            .with_brick_size(BrickSize::new([4, 4, 1, 1, 1, 1]));
+        let vds = VolumeDataAccess::create(dir.path().to_str().unwrap(), VdsMetadata::new(layout))
+            .await
+            .unwrap();
+
+        let mut values = vec![0.0f32; 64];
+        values[5 * 8 + 6] = 9.0;
+        values[7 * 8 + 4] = -7.0;
+        values[8 + 2] = 2.0;
+        vds.write_slice(&[0, 0], &[8, 8], &typed_data_to_bytes(&values))
+            .await
+            .unwrap();
+        vds
+    }
+
+    #[test]
+    fn test_value_query() {
+        let range = ValueRange::new(-3.0, 2.0);
+        assert!(!ValueQuery::Above(2.0).may_match(&range));
+        assert!(ValueQuery::Below(-2.0).may_match(&range));
+        assert!(ValueQuery::Between(1.5, 10.0).may_match(&range));
+        assert!(!ValueQuery::Between(2.5, 10.0).may_match(&range));
+        assert!(ValueQuery::AbsAbove(2.5).may_match(&range));
+        assert!(!ValueQuery::AbsAbove(3.0).may_match(&range));
+        assert!(!ValueQuery::Above(0.0).matches(f64::INFINITY));
+        assert!(ValueQuery::Between(1.0, 2.0).matches(2.0));
+    }
+
+    #[tokio::test]
+    async fn test_query_values() {
+        let dir = TempDir::new().unwrap();
+        let vds = create_test_volume(&dir).await;
+
+        // The index is built on first use
+        let index = vds.value_index().await.unwrap();
+        assert_eq!(index.candidate_bricks(ValueQuery::Above(1.0)), vec![0, 3]);
+        assert_eq!(index.brick_range(3), Some(ValueRange::new(-7.0, 9.0)));
+
+        let matches = vds
+            .query_values(ValueQuery::AbsAbove(5.0), &QueryOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(matches.voxels, vec![(vec![5, 6], 9.0), (vec![7, 4], -7.0)]);
+        assert_eq!((matches.bricks_scanned, matches.bricks_skipped), (1, 3));
+        assert!(!matches.truncated);
+
+        let options = QueryOptions::new()
+            .with_region(vec![0, 0], vec![6, 8])
+            .with_max_voxels(1);
+        let matches = vds
+            .query_values(ValueQuery::Above(1.0), &options)
+            .await
+            .unwrap();
+        assert_eq!(matches.count, 2);
+        assert_eq!(matches.voxels, vec![(vec![1, 2], 2.0)]);
+        assert!(matches.truncated);
+        assert_eq!(
+            vds.count_values(ValueQuery::Between(-1.0, 1.0))
+                .await
+                .unwrap(),
+            61
+        );
+
+        // Rewriting a brick invalidates the index
+        vds.write_brick(1, &typed_data_to_bytes(&[20.0f32; 16]))
+            .await
+            .unwrap();
+        assert!(vds.read_brick_index().await.unwrap().is_none());
+        assert_eq!(vds.count_values(ValueQuery::Above(10.0)).await.unwrap(), 16);
+    }
+}
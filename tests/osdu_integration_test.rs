@@ -4,7 +4,8 @@
 //! to validate compatibility with the OSDU data platform.
 
 use openvds::{
-    compression::{get_compressor, CompressionMethod},
+    compression::{get_compressor, CompressionMethod, Compressor},
+    filter::{Filter, FilteredCompressor},
     layout::BrickSize,
     types::{AxisDescriptor, DataType},
     VolumeDataLayout,
@@ -72,7 +73,7 @@ fn test_decompress_none_chunk() {
     println!("✓ Read uncompressed chunk: {} bytes", chunk_data.len());
 
     // No compression - data should be raw
-    let compressor = get_compressor(CompressionMethod::None);
+    let compressor = get_compressor(CompressionMethod::None).unwrap();
     let decompressed = compressor
         .decompress(&chunk_data, None)
         .expect("Failed to decompress None chunk");
@@ -113,7 +114,7 @@ fn test_decompress_zip_chunk() {
     println!("  Header size: {} bytes", HEADER_SIZE);
     println!("  Compressed data: {} bytes", compressed_data.len());
 
-    let compressor = get_compressor(CompressionMethod::Deflate);
+    let compressor = get_compressor(CompressionMethod::Deflate).unwrap();
     let decompressed = compressor
         .decompress(compressed_data, None)
         .expect("Failed to decompress ZIP chunk");
@@ -148,7 +149,7 @@ fn test_decompress_rle_chunk() {
 
     println!("✓ Read RLE compressed chunk: {} bytes", chunk_data.len());
 
-    let compressor = get_compressor(CompressionMethod::RLE);
+    let compressor = get_compressor(CompressionMethod::RLE).unwrap();
     let decompressed = compressor
         .decompress(&chunk_data, None)
         .expect("Failed to decompress RLE chunk");
@@ -251,3 +252,73 @@ fn test_compression_comparison() {
         }
     }
 }
+
+/// Measure shuffle/delta pre-filters in front of Deflate and Zstd on the uncompressed chunks
+#[test]
+#[ignore = "measurement; run with --ignored --nocapture"]
+fn test_filter_compression_ratios() {
+    // Uncompressed chunks carry the same 24-byte header as the ZIP chunk
+    const HEADER_SIZE: usize = 24;
+    let chunks = [
+        ("F32", "chunk.CompressionMethod_None", 4),
+        ("U16", "chunk.U16.CompressionMethod_None", 2),
+        ("U8", "chunk.U8.CompressionMethod_None", 1),
+    ];
+
+    println!("\n=== Filter Compression Ratios ===");
+    for (name, filename, size) in chunks {
+        let path = test_data_dir().join(filename);
+        if !path.exists() {
+            continue;
+        }
+        let chunk = fs::read(&path).expect("Failed to read chunk file");
+        let data = &chunk[HEADER_SIZE..];
+
+        let chains = [
+            ("none", vec![]),
+            ("byte-shuffle", vec![Filter::ByteShuffle(size)]),
+            ("bit-shuffle", vec![Filter::BitShuffle(size)]),
+            ("delta", vec![Filter::Delta(size)]),
+            (
+                "byte-shuffle+delta",
+                vec![Filter::ByteShuffle(size), Filter::Delta(1)],
+            ),
+        ];
+        let mut ratios = Vec::new();
+        for method in [CompressionMethod::Deflate, CompressionMethod::Zstd] {
+            for (label, filters) in &chains {
+                let compressor =
+                    FilteredCompressor::new(filters.clone(), get_compressor(method).unwrap());
+                let compressed = compressor.compress(data).expect("Failed to compress chunk");
+                let decompressed = compressor
+                    .decompress(&compressed, Some(data.len()))
+                    .expect("Failed to decompress chunk");
+                assert_eq!(decompressed, data);
+
+                let ratio = data.len() as f64 / compressed.len() as f64;
+                println!(
+                    "{:3} {:7} {:18} : {:.3}x",
+                    name,
+                    format!("{:?}", method),
+                    label,
+                    ratio
+                );
+                ratios.push(((method, *label), ratio));
+            }
+        }
+
+        if name == "F32" {
+            let ratio = |method, label| {
+                ratios
+                    .iter()
+                    .find(|(key, _)| *key == (method, label))
+                    .unwrap()
+                    .1
+            };
+            for method in [CompressionMethod::Deflate, CompressionMethod::Zstd] {
+                assert!(ratio(method, "byte-shuffle") > ratio(method, "none"));
+                assert!(ratio(method, "bit-shuffle") > ratio(method, "none"));
+            }
+        }
+    }
+}